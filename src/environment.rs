@@ -0,0 +1,62 @@
+use crate::interpreter::Value;
+use std::collections::HashMap;
+
+// The variables one function call can see, kept as a chain of scopes. Every block that's running has its own
+// scope at the end of the chain: `let` always binds in the innermost scope, while lookups and assignments walk
+// outwards from it until they find the name.
+#[derive(Debug, Clone)]
+pub struct Environment {
+  scopes: Vec<HashMap<String, Value>>, // outermost first, the scope of the function body itself is always there
+}
+
+impl Default for Environment {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl Environment {
+  pub fn new() -> Environment {
+    Environment { scopes: vec![HashMap::new()] }
+  }
+
+  // Opens a scope for a block that's starting
+  pub fn push_scope(&mut self) {
+    self.scopes.push(HashMap::new());
+  }
+
+  // Drops the innermost scope and everything bound in it once its block is done. The outermost scope stays.
+  pub fn pop_scope(&mut self) {
+    if self.scopes.len() > 1 {
+      self.scopes.pop();
+    }
+  }
+
+  // Whether a block's scope is open, on top of the outermost one
+  pub fn in_block(&self) -> bool {
+    self.scopes.len() > 1
+  }
+
+  // Binds `name` in the innermost scope, shadowing any binding further out
+  pub fn define(&mut self, name: String, value: Value) {
+    if let Some(scope) = self.scopes.last_mut() {
+      scope.insert(name, value);
+    }
+  }
+
+  // The value of the closest binding of `name`
+  pub fn get(&self, name: &str) -> Option<&Value> {
+    self.scopes.iter().rev().find_map(|scope| scope.get(name))
+  }
+
+  // Updates the closest binding of `name`, returns false when there is none
+  pub fn assign(&mut self, name: &str, value: Value) -> bool {
+    match self.scopes.iter_mut().rev().find_map(|scope| scope.get_mut(name)) {
+      Some(binding) => {
+        *binding = value;
+        true
+      }
+      None => false,
+    }
+  }
+}
@@ -0,0 +1,106 @@
+use crate::interpreter::Value;
+use crate::parser::{ParseError, Span};
+use std::fmt;
+
+// Everything that can go wrong while parsing or running an Asa program. Each error carries the name or value
+// involved and, when known, the span of the source that caused it.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AsaError {
+  UndefinedVariable { name: String, span: Option<Span> },
+  UndefinedFunction { name: String, span: Option<Span> },
+  TypeMismatch { expected: String, found: Value, span: Option<Span> }, // an operation got a value of the wrong type
+  ArityMismatch { name: String, expected: usize, found: usize, span: Option<Span> }, // wrong number of arguments in a call
+  DivisionByZero { span: Option<Span> },
+  Overflow { operator: String, span: Option<Span> }, // the result of the operation doesn't fit in 64 bits, or is too big to work out at all
+  NegativeExponent { exponent: Value, span: Option<Span> }, // `^` only raises to powers of 0 or more
+  Conversion { found: Value, target: String, span: Option<Span> }, // the value has no equivalent of the target type
+  UnknownOperator { operator: String, span: Option<Span> },
+  UnexpectedNode { expected: String, span: Option<Span> }, // the parse tree isn't shaped the way the interpreter expects
+  MisplacedControl { keyword: String, span: Option<Span> }, // `break` or `continue` outside of a loop
+  ControlInExpression { keyword: String, span: Option<Span> }, // `return`, `break` or `continue` in an `if` used as a value
+  ParseError { message: String, span: Option<Span> },
+}
+
+impl AsaError {
+  // Where in the source the error happened, if known
+  pub fn span(&self) -> Option<Span> {
+    match self {
+      AsaError::UndefinedVariable { span, .. } |
+      AsaError::UndefinedFunction { span, .. } |
+      AsaError::TypeMismatch { span, .. } |
+      AsaError::ArityMismatch { span, .. } |
+      AsaError::DivisionByZero { span } |
+      AsaError::Overflow { span, .. } |
+      AsaError::NegativeExponent { span, .. } |
+      AsaError::Conversion { span, .. } |
+      AsaError::UnknownOperator { span, .. } |
+      AsaError::UnexpectedNode { span, .. } |
+      AsaError::MisplacedControl { span, .. } |
+      AsaError::ControlInExpression { span, .. } |
+      AsaError::ParseError { span, .. } => *span,
+    }
+  }
+
+  // Returns the same error pointing at `span` instead
+  pub fn with_span(mut self, new_span: Option<Span>) -> AsaError {
+    match &mut self {
+      AsaError::UndefinedVariable { span, .. } |
+      AsaError::UndefinedFunction { span, .. } |
+      AsaError::TypeMismatch { span, .. } |
+      AsaError::ArityMismatch { span, .. } |
+      AsaError::DivisionByZero { span } |
+      AsaError::Overflow { span, .. } |
+      AsaError::NegativeExponent { span, .. } |
+      AsaError::Conversion { span, .. } |
+      AsaError::UnknownOperator { span, .. } |
+      AsaError::UnexpectedNode { span, .. } |
+      AsaError::MisplacedControl { span, .. } |
+      AsaError::ControlInExpression { span, .. } |
+      AsaError::ParseError { span, .. } => *span = new_span,
+    }
+    self
+  }
+
+  // The message followed by the source line the error points at, with the offending part underlined
+  pub fn render(&self, source: &str) -> String {
+    match self.span() {
+      Some(span) => format!("{}\n{}", self, span.underline(source)),
+      None => self.to_string(),
+    }
+  }
+}
+
+// Prints the error as `line:column: message`, or just the message when there is no span
+impl fmt::Display for AsaError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    if let Some(span) = self.span() {
+      write!(f, "{}: ", span)?;
+    }
+    match self {
+      AsaError::UndefinedVariable { name, .. } => write!(f, "undefined variable '{}'", name),
+      AsaError::UndefinedFunction { name, .. } => write!(f, "undefined function '{}'", name),
+      AsaError::TypeMismatch { expected, found, .. } => write!(f, "expected {}, found {} {}", expected, found.type_name(), found),
+      AsaError::ArityMismatch { name, expected, found, .. } => {
+        write!(f, "function '{}' takes {} argument{} but {} {} given", name, expected, if *expected == 1 { "" } else { "s" }, found, if *found == 1 { "was" } else { "were" })
+      },
+      AsaError::DivisionByZero { .. } => write!(f, "division by zero"),
+      AsaError::Overflow { operator, .. } => write!(f, "arithmetic overflow in '{}'", operator),
+      AsaError::NegativeExponent { exponent, .. } => write!(f, "negative exponent {} in '^'", exponent),
+      AsaError::Conversion { found, target, .. } => write!(f, "can't convert {} {} to {}", found.type_name(), found, target),
+      AsaError::UnknownOperator { operator, .. } => write!(f, "unknown operator '{}'", operator),
+      AsaError::UnexpectedNode { expected, .. } => write!(f, "expected {}", expected),
+      AsaError::MisplacedControl { keyword, .. } => write!(f, "`{}` outside of a loop", keyword),
+      AsaError::ControlInExpression { keyword, .. } => write!(f, "`{}` inside an `if` expression", keyword),
+      AsaError::ParseError { message, .. } => write!(f, "{}", message),
+    }
+  }
+}
+
+impl std::error::Error for AsaError {}
+
+// A syntax error keeps its message and points at the token the parser stopped on
+impl From<ParseError> for AsaError {
+  fn from(error: ParseError) -> AsaError {
+    AsaError::ParseError { message: format!("expected {}, found {}", error.expected, error.found), span: Some(error.span) }
+  }
+}
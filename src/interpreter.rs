@@ -1,91 +1,337 @@
-use crate::parser::Node;
+use crate::environment::Environment;
+use crate::error::AsaError;
+use crate::parser::{Node, Span};
+use num_bigint::BigInt;
+use num_integer::Integer;
+use num_traits::{FromPrimitive, Signed, ToPrimitive};
 use std::collections::HashMap;
 use std::fmt::Display;
 
 #[derive(Debug, PartialEq, PartialOrd, Clone)]
 pub enum Value {
   String(String),
-  Number(i32),
+  Number(i64),
+  BigInt(BigInt), // a number too big for 64 bits, smaller ones are always a `Number`
+  Float(f64),
   Bool(bool),
+  Unit, // no value, what statements like loops, function definitions or an if where no branch ran evaluate to
+}
+
+impl Value {
+  // The name of the value's type, as used in error messages
+  pub fn type_name(&self) -> &'static str {
+    match self {
+      Value::String(_) => "string",
+      Value::Number(_) | Value::BigInt(_) => "number",
+      Value::Float(_) => "float",
+      Value::Bool(_) => "bool",
+      Value::Unit => "unit",
+    }
+  }
+}
+
+// Prints values the way they would be written in an Asa program, minus the quotes around strings
+impl Display for Value {
+  fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+    match self {
+      Value::String(value) => write!(f, "{}", value),
+      Value::Number(value) => write!(f, "{}", value),
+      Value::BigInt(value) => write!(f, "{}", value),
+      Value::Float(value) => write!(f, "{:?}", value), // always with a fraction or exponent, so it can't be mistaken for a number
+      Value::Bool(value) => write!(f, "{}", value),
+      Value::Unit => write!(f, "()"),
+    }
+  }
+}
+
+// A big integer that fits in 64 bits becomes a plain `Number`, so every number has only one representation
+impl From<BigInt> for Value {
+  fn from(value: BigInt) -> Value {
+    match value.to_i64() {
+      Some(value) => Value::Number(value),
+      None => Value::BigInt(value),
+    }
+  }
+}
+
+// What running a statement tells the block it's in to do next. Leaving a loop or a function early is ordinary
+// control flow, so it comes back as a value instead of going through the error channel. Anything other than
+// `Next` stops the block and is handed outwards until a loop or a function call deals with it.
+enum Flow {
+  Next(Value),         // carry on with the next statement, the value is what this one evaluated to
+  Break(Span),         // `break` was hit, leave the innermost loop
+  Continue(Span),      // `continue` was hit, go on with the next pass of the innermost loop
+  Return(Value),       // `return` was hit, leave the function with this value
+}
+
+// What arithmetic does when a result, or a number literal, doesn't fit in 64 bits
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Arithmetic {
+  #[default]
+  Promoting, // carry on with a big integer, which grows as large as it needs to
+  Checked,   // stop with an overflow error
+  Wrapping,  // wrap around, like two's complement hardware does
 }
 
 pub struct Runtime {
   functions: HashMap<String, Vec<Node>>, // mapping b/w name of fn & nodes in that fn 
-  stack: Vec<HashMap<String, Value>>, // hashmap 
+  globals: Environment, // variables defined at the top level of the program, every function can see them
+  top_level: Environment, // the scopes of blocks in top level code, which only that code can see
+  stack: Vec<Environment>, // the variables of every function call that hasn't returned yet, the running one last
+  arithmetic: Arithmetic, // how overflow is handled
+}
+
+impl Default for Runtime {
+  fn default() -> Self {
+    Self::new()
+  }
 }
 
 impl Runtime {
   pub fn new() -> Runtime {
+    Runtime::with_arithmetic(Arithmetic::Promoting)
+  }
+
+  // A runtime whose arithmetic handles overflow the given way. Division by zero and negative exponents are errors
+  // either way.
+  pub fn with_arithmetic(arithmetic: Arithmetic) -> Runtime {
     Runtime {
       functions: HashMap::new(),
+      globals: Environment::new(),
+      top_level: Environment::new(),
       stack: Vec::new(),
+      arithmetic,
+    }
+  }
+
+  // Runs a program as a script: every top level item in order, followed by `main` when the program defines one.
+  // The result is what `main` returns, or the value of the last item for a program without `main`. A `return` at the
+  // top level ends the script there, `main` included.
+  pub fn start(&mut self, node: &Node) -> Result<Value, AsaError> {
+    let value = match self.run_program(node)? {
+      Flow::Return(value) => return Ok(value),
+      flow => Runtime::finish(flow)?,
+    };
+    if !self.functions.contains_key("main") {
+      return Ok(value);
     }
+    let start_main = Node::FunctionCall{name: "main".to_string(), children: vec![], span: node.span()};
+    self.run(&start_main)
   }
 
-  pub fn run(&mut self, node: &Node) -> Result<Value, String> {
+  // Runs a program's top level items without calling `main` afterwards. Functions and variables defined by one
+  // call are still there for the next, which is what the REPL needs. Returns the value of the last item.
+  pub fn eval(&mut self, node: &Node) -> Result<Value, AsaError> {
+    self.run(node)
+  }
+
+  // The scopes of the code that's running: the function call's, or the top level's outside of any call
+  fn frame(&self) -> &Environment {
+    self.stack.last().unwrap_or(&self.top_level)
+  }
+
+  fn frame_mut(&mut self) -> &mut Environment {
+    match self.stack.last_mut() {
+      Some(frame) => frame,
+      None => &mut self.top_level,
+    }
+  }
+
+  // Where new variables go: the function call that's running, the innermost block of top level code, or the global
+  // scope for top level code outside of any block
+  fn environment(&mut self) -> &mut Environment {
+    match self.stack.last_mut() {
+      Some(frame) => frame,
+      None if self.top_level.in_block() => &mut self.top_level,
+      None => &mut self.globals,
+    }
+  }
+
+  // Looks a variable up in the running code's own scopes first and falls back to the globals, so a local variable
+  // or parameter shadows a global with the same name. A function never sees the blocks of the code that called it.
+  fn lookup(&self, name: &str) -> Option<&Value> {
+    self.frame().get(name).or_else(|| self.globals.get(name))
+  }
+
+  // Updates the variable `lookup` would find, returns false when there is no such variable
+  fn assign(&mut self, name: &str, value: Value) -> bool {
+    let frame = self.frame_mut();
+    if frame.get(name).is_some() {
+      return frame.assign(name, value);
+    }
+    self.globals.assign(name, value)
+  }
+
+  // Runs `f` with a new scope open, the scope is closed again whether `f` succeeds or not
+  fn scoped<T>(&mut self, f: impl FnOnce(&mut Runtime) -> Result<T, AsaError>) -> Result<T, AsaError> {
+    self.frame_mut().push_scope();
+    let result = f(self);
+    self.frame_mut().pop_scope();
+    result
+  }
+
+  // Functions are registered before anything else runs, so every part of the program can call them. Then the other
+  // top level items run in order in the global scope and the program's value is the value of the last one. A `return`
+  // stops the program early with its value, and `break` or `continue` that got out of a top level loop stop it too.
+  fn run_program(&mut self, node: &Node) -> Result<Flow, AsaError> {
+    let children = match node {
+      Node::Program{children, ..} => children,
+      _ => return Ok(Flow::Next(self.run(node)?)),
+    };
+    for n in children {
+      if let Node::FunctionDefine{..} = n {
+        self.run(n)?;
+      }
+    }
+    let mut value = Value::Unit;
+    for n in children {
+      let flow = match n {
+        Node::FunctionDefine{..} => Flow::Next(Value::Unit), // already registered above
+        _ => self.exec(n)?,
+      };
+      match flow {
+        Flow::Next(result) => value = result,
+        flow => return Ok(flow),
+      }
+    }
+    Ok(Flow::Next(value))
+  }
+
+  // Runs one statement of a block and says how the block should go on
+  fn exec(&mut self, node: &Node) -> Result<Flow, AsaError> {
     match node {
-      Node::Program{children} => {
-        for n in children {
-          match n { // calls functions depending on node type thats matched 
-            Node::FunctionDefine{..} => {
-              self.run(n);
-            },
-            Node::IfElseStatements{..} => {
-                self.functions.insert("main".to_string(), vec![Node::FunctionReturn{children: vec![n.clone()]}]);
-              },
-            Node::ConditionalExpression{..} => {
-                self.functions.insert("main".to_string(), vec![Node::FunctionReturn{children: vec![n.clone()]}]);
-              },
-            Node::Expression{..} => {
-              self.functions.insert("main".to_string(), vec![Node::FunctionReturn{children: vec![n.clone()]}]);
-            },
-            Node::Statement{..} => {
-              self.functions.insert("main".to_string(), vec![n.clone()]);
-            }
-            _ => (),
+      Node::BreakStatement{span} => Ok(Flow::Break(*span)),
+      Node::ContinueStatement{span} => Ok(Flow::Continue(*span)),
+      Node::Statement{children, ..} if matches!(children[0], Node::FunctionReturn{..}) => Ok(Flow::Return(self.run(&children[0])?)),
+      Node::IfElseStatements{children, ..} => self.exec_if(children),
+      Node::WhileStatement{children, ..} => self.exec_while(children),
+      Node::ForStatement{children, ..} => self.exec_for(children),
+      _ => Ok(Flow::Next(self.run(node)?)),
+    }
+  }
+
+  // Runs the statements of a block in a scope of its own. Stops early at the first `break`, `continue` or `return`
+  // and hands it outwards, otherwise the block's value is the value of its last statement.
+  fn run_block(&mut self, statements: &[Node]) -> Result<Flow, AsaError> {
+    self.scoped(|runtime| {
+      let mut value = Value::Unit;
+      for n in statements {
+        match runtime.exec(n)? {
+          Flow::Next(result) => value = result,
+          flow => return Ok(flow),
+        }
+      }
+      Ok(Flow::Next(value))
+    })
+  }
+
+  // Evaluates the condition of an if or a loop, or an operand of a logical operator, which has to come out as a bool
+  fn condition(&mut self, node: &Node) -> Result<bool, AsaError> {
+    match self.run(node)? {
+      Value::Bool(value) => Ok(value),
+      found => Err(AsaError::TypeMismatch { expected: "bool".to_string(), found, span: Some(node.span()) }),
+    }
+  }
+
+  // Runs the block of the first branch whose condition holds, or the `else` block when none of them does. Without
+  // an `else` it's possible that no block runs, then the if chain has no value.
+  fn exec_if(&mut self, branches: &[Node]) -> Result<Flow, AsaError> {
+    for branch in branches {
+      match branch {
+        Node::IfStatement{children, ..} | Node::ElseIfStatement{children, ..} => {
+          if self.condition(&children[0])? {
+            return self.run_block(&children[1..]);
           }
+        },
+        Node::ElseStatement{children, ..} => return self.run_block(children),
+        _ => return Err(AsaError::UnexpectedNode { expected: "an if, else if or else block".to_string(), span: Some(branch.span()) }),
+      }
+    }
+    Ok(Flow::Next(Value::Unit))
+  }
+
+  // Checks the condition before every pass through the body and stops once it's false or the body breaks out.
+  // A `return` in the body leaves the loop and is handed on to the function.
+  fn exec_while(&mut self, children: &[Node]) -> Result<Flow, AsaError> {
+    let (condition, body) = children.split_at(1);
+    while self.condition(&condition[0])? {
+      // every pass gets a scope of its own, so nothing bound in one pass is left over for the next
+      match self.run_block(body)? {
+        Flow::Break(_) => break,
+        Flow::Next(_) | Flow::Continue(_) => (),
+        flow => return Ok(flow),
+      }
+    }
+    Ok(Flow::Next(Value::Unit))
+  }
+
+  // Runs the body once for every number in the range. Both ends are evaluated before the first pass, and the loop
+  // variable is bound again at the start of every pass so the body can't change how many passes there are.
+  fn exec_for(&mut self, children: &[Node]) -> Result<Flow, AsaError> {
+    let name = match &children[0] {
+        Node::Identifier { value, .. } => value.clone(),
+        _ => return Err(AsaError::UnexpectedNode { expected: "a loop variable".to_string(), span: Some(children[0].span()) }),
+    };
+    let (op, ends) = match &children[1] {
+        Node::Range { name, children, .. } => (name, children),
+        _ => return Err(AsaError::UnexpectedNode { expected: "a range".to_string(), span: Some(children[1].span()) }),
+    };
+    let mut bounds = vec![];
+    for end in ends {
+        match self.run(end)? {
+            Value::Number(value) => bounds.push(i128::from(value)),
+            // the loop variable is always a plain number, so there's no counting up to or down from a big one
+            found @ Value::BigInt(_) => return Err(AsaError::Conversion { found, target: "a loop counter".to_string(), span: Some(end.span()) }),
+            found => return Err(AsaError::TypeMismatch { expected: "number".to_string(), found, span: Some(end.span()) }),
+        }
+    }
+    // counting in i128 lets `..=` run right up to the largest number without the counter overflowing
+    let (start, end) = (bounds[0], if op == "..=" { bounds[1] + 1 } else { bounds[1] });
+    for i in start..end {
+        // the loop variable lives in a scope around the pass's own, which shadows anything outside the loop with its name
+        let flow = self.scoped(|runtime| {
+            runtime.environment().define(name.clone(), Value::Number(i as i64));
+            runtime.run_block(&children[2..])
+        })?;
+        match flow {
+            Flow::Break(_) => break,
+            Flow::Next(_) | Flow::Continue(_) => (),
+            flow => return Ok(flow),
         }
-        Ok(Value::Bool(true))
+    }
+    Ok(Flow::Next(Value::Unit))
+  }
+
+  // Turns what a function body or a program ended with into its value. Loop control that got this far wasn't inside
+  // a loop.
+  fn finish(flow: Flow) -> Result<Value, AsaError> {
+    match flow {
+      Flow::Next(value) | Flow::Return(value) => Ok(value),
+      Flow::Break(span) => Err(AsaError::MisplacedControl { keyword: "break".to_string(), span: Some(span) }),
+      Flow::Continue(span) => Err(AsaError::MisplacedControl { keyword: "continue".to_string(), span: Some(span) }),
+    }
+  }
+
+  pub fn run(&mut self, node: &Node) -> Result<Value, AsaError> {
+    match node {
+      Node::Program{..} => {
+        let flow = self.run_program(node)?;
+        Runtime::finish(flow)
       },
 
-      // If the `Node` is a `MathExpression`, evaluate it.
-      Node::MathExpression { name, children } => {
-        // Evaluate the left and right children of the `MathExpression`.
-        match (self.run(&children[0]), self.run(&children[1])) {
-            // If both children are `Number` values, extract their values and evaluate the expression.
-            (Ok(Value::Number(lhs)), Ok(Value::Number(rhs))) => {
-                match name.as_ref() {
-                    // If the operator is `+`, add the values.
-                    "+" => Ok(Value::Number(lhs + rhs)),
-                    // If the operator is `-`, subtract the values.
-                    "-" => Ok(Value::Number(lhs - rhs)),
-                    // If the operator is `*`, multiply the values.
-                    "*" => Ok(Value::Number(lhs * rhs)),
-                    // If the operator is `/`, divide the values.
-                    "/" => Ok(Value::Number(lhs / rhs)),
-                    // If the operator is `^`, raise the left value to the power of the right value.
-                    "^" => {
-                        let mut result = 1;
-                        for i in 0..rhs {
-                            result = result * lhs;
-                        }
-                        Ok(Value::Number(result))
-                    },
-                    // If the operator is not recognized, return an error message.
-                    _ => Err("Undefined operator".to_string()),
-                }
-            }
-            // If either child is not a `Number` value, return an error message.
-            _ => Err("Cannot do math on String or Bool".to_string()),
-        }
+      // If the `Node` is a `MathExpression`, evaluate both of its children and apply the operator to them.
+      Node::MathExpression { name, children, span } => {
+        let lhs = self.run(&children[0])?;
+        let rhs = self.run(&children[1])?;
+        math(name, lhs, rhs, *span, self.arithmetic)
     },
 
        // If the `Node` is a `FunctionCall`, evaluate it.
-       Node::FunctionCall { name, children } => {
+       Node::FunctionCall { name, children, span } => {
         // Extract the input arguments.
-        let in_args = if children.len() > 0 {
+        let in_args = if !children.is_empty() {
             match &children[0] {
-                Node::FunctionArguments { children } => {
+                Node::FunctionArguments { children, .. } => {
                     children
                 },
                 _ => children,
@@ -94,346 +340,428 @@ impl Runtime {
             children
         };
         // Create a new frame for local variables.
-        let mut new_frame = HashMap::new();
-       let mut val = Value::Bool(true); // initialises val to true
-
-       // Save a raw pointer to the `Runtime` instance for use in the nested closure.
-        let rt = self as *mut Runtime;
-        // Find the named function and evaluate its body.
-        match self.functions.get(name) {
-            Some(statements) => {
-                {
-                    // If the function has input arguments, bind their values to the corresponding parameters.
-                    match statements[0].clone() {
-                        Node::FunctionArguments { children } => {
-                            for (ix, arg) in children.iter().enumerate() {
-                                // Use unsafe Rust code to call `run` on the input argument and handle any errors.
-                                unsafe {
-                                    let result = (*rt).run(&in_args[ix])?;
-                                    match arg {
-                                        Node::Expression { children } => {
-                                            match &children[0] {
-                                                Node::Identifier { value } => {
-                                                    new_frame.insert(value.clone(), result);
-                                                },
-                                                _ => (),
-                                            }
-                                        }
-                                        _ => (),
-                                    }
-                                }
-                            }
-                        }
-                        _ => (),
+        let mut new_frame = Environment::new();
+
+        // Find the named function and evaluate its body. The body is cloned so it can be run while `self` is borrowed mutably.
+        if let Some(statements) = self.functions.get(name).cloned() {
+            // If the function has input arguments, bind their values to the corresponding parameters.
+            let params = match statements.first() {
+                Some(Node::FunctionArguments { children, .. }) => children.clone(),
+                _ => vec![],
+            };
+            if params.len() != in_args.len() {
+                return Err(AsaError::ArityMismatch { name: name.clone(), expected: params.len(), found: in_args.len(), span: Some(*span) });
+            }
+            for (param, arg) in params.iter().zip(in_args) {
+                let result = self.run(arg)?;
+                if let Node::Expression { children, .. } = param {
+                    if let Node::Identifier { value, .. } = &children[0] {
+                        new_frame.define(value.clone(), result);
                     }
                 }
-                // Push the new frame onto the stack.
-                self.stack.push(new_frame);
-                // Evaluate each statement in the function body.
-                for n in statements.clone() {
-                   // result = self.run(&n);
-                   val = self.run(&n)?;
-                }
-                // Pop the frame off the stack.
-                self.stack.pop();
-                return Ok(val) 
-            },
-            None => (),
-        };
-        // Return the result of evaluating the function.
-       // result
-       Err("Undefined function".to_string())
-    },
-
-      Node::ConditionalValue{children} => { 
-        let lhs = match children[0] { // checks first argument in children is number, identfier, boolean or math expression for it to be valid conditional expression 
-            Node::Number { .. } |
-            Node::Identifier { .. } |
-            Node::Bool { .. } |
-            Node::MathExpression { .. } => {
-                self.run(&children[0]) 
-            },
-            _ => Err("Unknown Statement".to_string()), // if none of those nodes match, prints error 
-        };
-          lhs
-      },
-
-      Node::ConditionalOperator{value} => { // returns the conditional operator value as a string 
-        Ok(Value::String(value.clone()))
-       },
-      
-    
-      
-    Node::ConditionalExpression{children} => {
-         // Evaluates the left hand side & right hand side of conditional expressions 
-       let lhs_val = match &children[0]{  // matches children[0] to ConditionalValue node 
-            Node::ConditionalValue { .. } => {
-                self.run(&children[0])
-            },
-            _ => Err("Unknown value".to_string()), // if doesn't match, returns error message 
-        };
-
-        let op_val = match &children[1]{ // matches children[1] to ConditionalOperator node 
-            Node::ConditionalOperator { .. } => {
-                self.run(&children[1])
-            },
-            _ => Err("Unknown operator".to_string()), // if doesn't match, returns error message 
-        };
-        let rhs_val = match &children[2]{ // matches children[2] to ConditionalValue node 
-            Node::ConditionalValue { .. } => {
-                self.run(&children[2])
-            },
-            _ => Err("Unknown value".to_string()), // if doesn't match, returns error message 
-        };
-
-        let string_op_val = match op_val.unwrap(){ // unwraps the returned op_val to a string 
-            Value::String(value) => {
-                value
-            }, 
-            _ => ("Operator error".to_string())
-        };
-
-        let mut lhs_bool = false;
-        let mut rhs_number = false;
-        let mut lhs_number = false;
-        let mut rhs_bool = false;
-
-        // checks if lhs argument is a boolean or a number 
-        let check_lhs = match lhs_val.clone().unwrap(){
-            Value::Bool(lhs_val) => {
-                lhs_bool = true;
-            },
-            Value::Number(lhs) => {
-                lhs_number = true;
-            },
-            _ => (),
-        };
-
-        // checks if rhs argument is a boolean or a number 
-        let check_rhs = match rhs_val.clone().unwrap(){
-            Value::Bool(rhs_val) =>{
-                rhs_bool = true;
-            }, 
-            Value::Number(rhs_val) =>{
-                rhs_number = true;
-            }, 
-            _ => (),
+            }
+            // The parameter list was already bound above, so the body is everything after it.
+            let body = match statements.first() {
+                Some(Node::FunctionArguments { .. }) => &statements[1..],
+                _ => &statements[..],
+            };
+            // Push the new frame onto the stack and evaluate the body in it. A `return` anywhere in the body ends
+            // the call with its value, otherwise the call's value is the value of the body's last statement.
+            self.stack.push(new_frame);
+            let flow = self.run_block(body);
+            // Pop the frame off the stack, also when the call failed since the runtime may be used again.
+            self.stack.pop();
+            return Runtime::finish(flow?);
         };
-
-        // if lhs is a boolean & rhs is a number or rhs is a boolean & lhs is a number, returns error message
-        // cannot compare boolean and numbers 
-        if((lhs_bool && rhs_number) || (rhs_bool && lhs_number)){
-            return Err("Cannot compare these two values".to_string())
-        } 
-
-        // depending on the operator value, performs conditional operations on lhs & rhs and returns the result as a boolean
-        if (string_op_val == "<"){
-            Ok(Value::Bool(lhs_val < rhs_val))
-        } else if (string_op_val == ">"){
-            Ok(Value::Bool(lhs_val > rhs_val))
-        } else if (string_op_val == "=="){
-            Ok(Value::Bool(lhs_val == rhs_val))
-        } else if (string_op_val == "!="){
-            Ok(Value::Bool(lhs_val != rhs_val))
-        } else if (string_op_val == ">="){
-            Ok(Value::Bool(lhs_val >= rhs_val))
-        } else if (string_op_val == "<="){
-            Ok(Value::Bool(lhs_val <= rhs_val))
-        } else {
-            Err("Unknown operator".to_string()) // if none of these operators match, returns unkown operator error message
+        // Not a function the program defined, but it may be one of the builtins.
+        if BUILTINS.contains(&name.as_str()) {
+            if in_args.len() != 1 {
+                return Err(AsaError::ArityMismatch { name: name.clone(), expected: 1, found: in_args.len(), span: Some(*span) });
+            }
+            let arg = self.run(&in_args[0])?;
+            return builtin(name, arg, in_args[0].span());
         }
+       Err(AsaError::UndefinedFunction { name: name.clone(), span: Some(*span) })
     },
 
-      // Defines a new function based on the elements in the children argument. The name of the function is retrieved from the first element of the children, and the statements that define the function are retrieved from rest of hte children (head/tail). A new key-value pair is then inserted into the functions field of the current runtime object. If the function was successfully defined, the code returns a Value object with a boolean value of true, otherwise an error is returned.
-      Node::FunctionDefine{children} => { 
-        let (head, tail) = children.split_at(1);
-        match &head[0] {
-            Node::Identifier { value } => {
-                self.functions.insert(value.to_string(), tail.to_vec());
-            },
-            _ => (),
-        }
-        Ok(Value::Bool(true))
+      // If the `Node` is a `ConditionalExpression`, evaluate both of its children and compare them with the operator.
+      Node::ConditionalExpression { name, children, span } => {
+        let lhs = self.run(&children[0])?;
+        let rhs = self.run(&children[1])?;
+        compare(name, lhs, rhs, *span)
       },
 
-       Node::IfElseStatements{children} => { 
-        // loops through all elements in children and accordingly matches to respective nodes and calls run function  
-        for c in children{ 
-            match c {
-                Node::IfStatement { .. } => {
-                    self.run(&children[0])
-                },
-                Node::ElseIfStatement { .. } => {
-                    self.run(&children[0])
-                },
-                Node::ElseStatement { .. } => {
-                    self.run(&children[0])
-                },
-                _ => Err("Unknown Statement".to_string()), // if none match, returns error message 
-            };
-
+      // `&&` and `||` only evaluate their right hand side when the left hand side doesn't already decide the result
+      Node::LogicalExpression{name, children, span} => {
+        let lhs = self.condition(&children[0])?;
+        match name.as_str() {
+            "&&" if !lhs => Ok(Value::Bool(false)),
+            "||" if lhs => Ok(Value::Bool(true)),
+            "&&" | "||" => Ok(Value::Bool(self.condition(&children[1])?)),
+            _ => Err(AsaError::UnknownOperator { operator: name.clone(), span: Some(*span) }),
         }
-          return Ok(Value::Bool(true))
       },
 
-      // this node contains a conditional if statement and statements that need to be executed if condition is met
-      Node::IfStatement{children} => { 
-        let if_stat_cond = match children[0] { // matches children[0] to a ConditionalExpression 
-            Node::ConditionalExpression { .. } => {
-                self.run(&children[0])
-            },
-            _ => Err("Unknown Expression".to_string()), // returns error message if don't match 
-        };
-
-        let mut condition = false;
-        match &if_stat_cond.clone().unwrap() { // unwraps the result of the ConditionalExpression
-            Value::Bool (value) => {
-                condition = *value;
+      // `!` negates a bool, `-` and `+` give a number its sign
+      Node::UnaryExpression{name, children, span} => {
+        match name.as_str() {
+            "!" => Ok(Value::Bool(!self.condition(&children[0])?)),
+            "-" | "+" => match self.run(&children[0])? {
+                // negating the smallest number gives one that's too big to fit
+                Value::Number(value) if name == "-" => match value.checked_neg() {
+                    Some(result) => Ok(Value::Number(result)),
+                    None => fit(-BigInt::from(value), name, *span, self.arithmetic),
+                },
+                Value::BigInt(value) if name == "-" => fit(-value, name, *span, self.arithmetic),
+                value @ (Value::Number(_) | Value::BigInt(_)) => Ok(value),
+                Value::Float(value) if name == "-" => Ok(Value::Float(-value)),
+                Value::Float(value) => Ok(Value::Float(value)),
+                found => Err(AsaError::TypeMismatch { expected: "number".to_string(), found, span: Some(children[0].span()) }),
             },
-            _ => (),
+            _ => Err(AsaError::UnknownOperator { operator: name.clone(), span: Some(*span) }),
         }
-
-        // if the condition was met, executes the statements inside the if block 
-        if(condition){
-            for c in children{
-                let result = match c { // matches the children to Statement node 
-                    Node::Statement { .. } => {
-                        self.run(&c)
-                    },
-                    _ => Err("Unknown Statement".to_string()), // returns error message if don't match 
-                };    
-            }
-        } 
-        if_stat_cond
       },
 
-      // this node contains a conditional else if statement and statements that need to be executed if condition is met
-      Node::ElseIfStatement{children} => { 
-        let else_if_stat_cond = match children[0] { // matches children[0] to a ConditionalExpression 
-            Node::ConditionalExpression { .. } => {
-                self.run(&children[0])
-            },
-            _ => Err("Unknown Statement".to_string()), // returns error message if don't match 
-        };
-
-        let mut condition = false;
-        match &else_if_stat_cond.clone().unwrap() { // unwraps the result of the ConditionalExpression
-            Value::Bool (value) => {
-                condition = *value;
-            },
-            _ => (),
+      // Defines a new function based on the elements in the children argument. The name of the function is retrieved from the first element of the children, and the statements that define the function are retrieved from rest of hte children (head/tail). A new key-value pair is then inserted into the functions field of the current runtime object. A definition has no value of its own.
+      Node::FunctionDefine{children, ..} => { 
+        let (head, tail) = children.split_at(1);
+        if let Node::Identifier { value, .. } = &head[0] {
+            self.functions.insert(value.to_string(), tail.to_vec());
         }
-
-        // if the condition was met, executes the statements inside the else if block 
-        if(condition){
-            for c in children{
-                let result = match c {  // matches each element to Statement node 
-                    Node::Statement { .. } => {
-                        self.run(&c)
-                    },
-                    _ => Err("Unknown Statement".to_string()), // returns error message if don't match 
-                };    
-            }
-         }
-        else_if_stat_cond
+        Ok(Value::Unit)
       },
 
-
-      // this node contains statements that need to be executed 
-      Node::ElseStatement{children} => { 
-        for c in children{
-            let result = match c { // matches each element to Statement node 
-                Node::Statement { .. } => {
-                    self.run(&c)
-                },
-                _ => Err("Unknown Statement".to_string()), // returns error message if don't match 
-            };    
-        }
-        Ok(Value::Bool(true))
+      // An if chain or a loop outside of a function body, which is where a program's top level code runs. An if chain
+      // evaluates to the value of the block that ran: what it returned, or else the value of its last statement.
+      Node::IfElseStatements{..} | Node::WhileStatement{..} | Node::ForStatement{..} => {
+        let flow = self.exec(node)?;
+        Runtime::finish(flow)
       },
 
       // Calls the run method on the first element in the children argument, which recursively evaluates the AST of the program being executed and returns the resulting value or error message.
-      Node::FunctionReturn{children} => {
+      Node::FunctionReturn{children, ..} => {
         self.run(&children[0]) // recursively calls run method on elements in children 
       },
 
       // citation: HW 5 solutions interpreter.rs file = looked at Identifier for reference
        // If the `Node` is an `Identifier`, look up its value in the current frame.
-       Node::Identifier { value } => {
-        let last = self.stack.len() - 1;
-        match self.stack[last].get(value) {
+       Node::Identifier { value, span } => {
+        match self.lookup(value) {
             Some(id_value) => Ok(id_value.clone()),
-            None => Err("Undefined variable".to_string()),
+            None => Err(AsaError::UndefinedVariable { name: value.clone(), span: Some(*span) }),
         }
     },
 
 
       // Checks the type of the first element in the children argument and deciding what to do based on that type. If the type is a VariableDefine or FunctionReturn node, the code runs the run method on that node and returns the result.
-      Node::Statement{children} => { 
+      Node::Statement{children, ..} => { 
         match children[0] { // if children[0] matches VariableDefine or FunctionReturn, runs associated function 
           Node::VariableDefine { .. } |
+          Node::Assignment { .. } |
+          Node::Expression { .. } |
           Node::FunctionReturn { .. } => {
               self.run(&children[0])
           },
-          _ => Err("Unknown Statement".to_string()), // returns error message if doesn't match 
+          _ => Err(AsaError::UnexpectedNode { expected: "a statement".to_string(), span: Some(children[0].span()) }), // returns error message if doesn't match 
       }
       },
 
        // If the `Node` is a `VariableDefine`, evaluate its expression and bind the result to a new variable.
-       Node::VariableDefine { children } => {
+       Node::VariableDefine { children, .. } => {
         // Extract the variable name.
         let name: String = match &children[0] {
-            Node::Identifier { value } => value.clone(),
+            Node::Identifier { value, .. } => value.clone(),
             _ => "".to_string(),
         };
         // Evaluate the expression.
         let value = self.run(&children[1])?;
-        // Add the variable to the current frame.
-        let last = self.stack.len() - 1;
-        self.stack[last].insert(name, value.clone());
+        // Add the variable to the innermost scope.
+        self.environment().define(name, value.clone());
         // Return the value.
         Ok(value)
     }
 
-       Node::Expression { children } => {
+       // If the `Node` is an `Assignment`, update a variable that already exists. The compound forms like `+=` apply
+       // their operator to the variable's current value and the new one.
+       Node::Assignment { name: op, children, span } => {
+        let (name, name_span) = match &children[0] {
+            Node::Identifier { value, span } => (value.clone(), *span),
+            _ => return Err(AsaError::UnexpectedNode { expected: "a variable name".to_string(), span: Some(children[0].span()) }),
+        };
+        let current = match self.lookup(&name) {
+            Some(value) => value.clone(),
+            None => return Err(AsaError::UndefinedVariable { name, span: Some(name_span) }),
+        };
+        let value = self.run(&children[1])?;
+        let value = match op.strip_suffix('=') {
+            Some("") | None => value,
+            Some(operator) => math(operator, current, value, *span, self.arithmetic)?,
+        };
+        self.assign(&name, value.clone());
+        Ok(value)
+    }
+
+       Node::Expression { children, .. } => {
         match children[0] { // if children[0] matches MathExpression, Number, FunctionCall, String, Bool, or Identifier, runs the associated function
             Node::MathExpression { .. } |
             Node::Number { .. } |
+            Node::BigInt { .. } |
+            Node::Float { .. } |
             Node::FunctionCall { .. } |
             Node::String { .. } |
             Node::Bool { .. } |
-            Node::Identifier { .. } => {
+            Node::Identifier { .. } |
+            Node::ConditionalExpression { .. } |
+            Node::LogicalExpression { .. } |
+            Node::UnaryExpression { .. } => {
                 self.run(&children[0])
             },
-            _ => Err("Unknown Expression".to_string()), // returns error message if doesn't match 
+            // an if used as a value is whatever its chosen block evaluates to, it can't jump out of the code around it
+            Node::IfElseStatements { span, .. } => match self.exec(&children[0])? {
+                Flow::Next(value) => Ok(value),
+                Flow::Return(_) => Err(AsaError::ControlInExpression { keyword: "return".to_string(), span: Some(span) }),
+                Flow::Break(span) => Err(AsaError::ControlInExpression { keyword: "break".to_string(), span: Some(span) }),
+                Flow::Continue(span) => Err(AsaError::ControlInExpression { keyword: "continue".to_string(), span: Some(span) }),
+            },
+            _ => Err(AsaError::UnexpectedNode { expected: "an expression".to_string(), span: Some(children[0].span()) }), // returns error message if doesn't match 
         }
         }
 
-      Node::Number{value} => { 
+      Node::Number{value, ..} => { 
         Ok(Value::Number(*value)) // returns val assigned to that number 
       }
+
+      // a literal too big for 64 bits is held to the same arithmetic as a result that is, so it's an overflow error
+      // when overflow is checked and wraps around when it wraps
+      Node::BigInt{value, span} => fit(value.clone(), &value.to_string(), *span, self.arithmetic),
+
+      Node::Float{value, ..} => Ok(Value::Float(*value)),
     
-      Node::String{value} => { 
+      Node::String{value, ..} => { 
        Ok(Value::String(value.clone())) // returns string val 
       }
 
-      Node::Bool{value} => {
+      Node::Bool{value, ..} => {
         Ok(Value::Bool(*value)) // returns bool val
       }
        // If the `Node` is of an unhandled type, return an error message.
        _ => {
-        Err("Unhandled Node".to_string())
+        Err(AsaError::UnexpectedNode { expected: "a node the interpreter can run".to_string(), span: Some(node.span()) })
         },
     }
   }
 
 }
 
-pub fn start_interpreter(node: &Node) -> Result<Value, String> {  
-  let mut runtime = Runtime::new();
-  runtime.run(node);
-  let start_main = Node::FunctionCall{name: "main".to_string(), children: vec![]};
-  runtime.run(&start_main)
+// Applies the arithmetic operator `op` to two values, `span` is where the error points if the values aren't numbers
+fn math(op: &str, lhs: Value, rhs: Value, span: Span, arithmetic: Arithmetic) -> Result<Value, AsaError> {
+    match (lhs, rhs) {
+        // If both values are `Number` values, extract them and evaluate the expression.
+        (Value::Number(lhs), Value::Number(rhs)) => {
+            // every operation gives the wrapped around result along with whether it overflowed
+            let (result, overflowed) = match op {
+                // If the operator is `+`, add the values.
+                "+" => lhs.overflowing_add(rhs),
+                // If the operator is `-`, subtract the values.
+                "-" => lhs.overflowing_sub(rhs),
+                // If the operator is `*`, multiply the values.
+                "*" => lhs.overflowing_mul(rhs),
+                // If the operator is `/`, divide the values, or if it's `%` take the remainder of that division.
+                "/" | "%" if rhs == 0 => return Err(AsaError::DivisionByZero { span: Some(span) }),
+                "/" => floor_div(lhs, rhs),
+                "%" => (floor_mod(lhs, rhs), false),
+                // If the operator is `^`, raise the left value to the power of the right value.
+                "^" if rhs < 0 => return Err(AsaError::NegativeExponent { exponent: Value::Number(rhs), span: Some(span) }),
+                "^" => overflowing_pow(lhs, rhs),
+                // If the operator is not recognized, return an error message.
+                _ => return Err(AsaError::UnknownOperator { operator: op.to_string(), span: Some(span) }),
+            };
+            match arithmetic {
+                _ if !overflowed => Ok(Value::Number(result)),
+                Arithmetic::Promoting => big_math(op, BigInt::from(lhs), BigInt::from(rhs), span).map(Value::from),
+                Arithmetic::Checked => Err(AsaError::Overflow { operator: op.to_string(), span: Some(span) }),
+                Arithmetic::Wrapping => Ok(Value::Number(result)),
+            }
+        }
+        // If either value is a big integer, the other one is turned into a big integer as well.
+        (Value::BigInt(lhs), Value::BigInt(rhs)) => fit(big_math(op, lhs, rhs, span)?, op, span, arithmetic),
+        (Value::Number(lhs), Value::BigInt(rhs)) => fit(big_math(op, BigInt::from(lhs), rhs, span)?, op, span, arithmetic),
+        (Value::BigInt(lhs), Value::Number(rhs)) => fit(big_math(op, lhs, BigInt::from(rhs), span)?, op, span, arithmetic),
+        // If either value is a `Float`, the other one is turned into a float as well.
+        (Value::Float(lhs), Value::Float(rhs)) => float_math(op, lhs, rhs, span),
+        (Value::Number(lhs), Value::Float(rhs)) => float_math(op, lhs as f64, rhs, span),
+        (Value::Float(lhs), Value::Number(rhs)) => float_math(op, lhs, rhs as f64, span),
+        (Value::BigInt(lhs), Value::Float(rhs)) => float_math(op, big_to_float(&lhs), rhs, span),
+        (Value::Float(lhs), Value::BigInt(rhs)) => float_math(op, lhs, big_to_float(&rhs), span),
+        // If either value is not a number or a float, return an error message.
+        (Value::Number(_) | Value::BigInt(_) | Value::Float(_), found) | (found, _) => Err(AsaError::TypeMismatch { expected: "number".to_string(), found, span: Some(span) }),
+    }
+}
+
+// The exact result of an operation on two integers. Big integers grow as needed so nothing overflows, except that
+// `^` gives up with an overflow error on a power too big to work out.
+fn big_math(op: &str, lhs: BigInt, rhs: BigInt, span: Span) -> Result<BigInt, AsaError> {
+    match op {
+        "+" => Ok(lhs + rhs),
+        "-" => Ok(lhs - rhs),
+        "*" => Ok(lhs * rhs),
+        "/" | "%" if rhs.bits() == 0 => Err(AsaError::DivisionByZero { span: Some(span) }),
+        "/" => Ok(lhs.div_floor(&rhs)),
+        "%" => Ok(lhs.mod_floor(&rhs)),
+        "^" if rhs.is_negative() => Err(AsaError::NegativeExponent { exponent: Value::from(rhs), span: Some(span) }),
+        "^" => big_pow(&lhs, &rhs).ok_or_else(|| AsaError::Overflow { operator: op.to_string(), span: Some(span) }),
+        _ => Err(AsaError::UnknownOperator { operator: op.to_string(), span: Some(span) }),
+    }
+}
+
+// The most bits a power can have, anything bigger takes too long to work out or to print
+const MAX_POWER_BITS: u64 = 1 << 20;
+
+// `lhs ^ rhs` for an exponent of 0 or more, None when the result would have more than `MAX_POWER_BITS` bits
+fn big_pow(lhs: &BigInt, rhs: &BigInt) -> Option<BigInt> {
+    // 0, 1 and -1 stay that small for any exponent, only whether it's 0, odd or even makes a difference
+    if lhs.bits() <= 1 {
+        let exponent = if rhs.bits() == 0 { 0 } else if rhs.is_odd() { 1 } else { 2 };
+        return Some(lhs.pow(exponent));
+    }
+    let exponent = rhs.to_u32()?;
+    if lhs.bits().saturating_mul(u64::from(exponent)) > MAX_POWER_BITS {
+        return None;
+    }
+    Some(lhs.pow(exponent))
+}
+
+// Turns the exact result of an operation on big integers into a value. It's a plain number when it fits in 64 bits,
+// otherwise the runtime's arithmetic decides: keep the big integer, fail, or keep only the lowest 64 bits.
+fn fit(value: BigInt, op: &str, span: Span, arithmetic: Arithmetic) -> Result<Value, AsaError> {
+    match arithmetic {
+        Arithmetic::Promoting => Ok(Value::from(value)),
+        _ if value.to_i64().is_some() => Ok(Value::from(value)),
+        Arithmetic::Checked => Err(AsaError::Overflow { operator: op.to_string(), span: Some(span) }),
+        // the bits are taken the way two's complement stores them, so this is the result the `overflowing_` operations wrap around to
+        Arithmetic::Wrapping => Ok(Value::Number((value & BigInt::from(u64::MAX)).to_u64().unwrap_or_default() as i64)),
+    }
+}
+
+// A big integer as the closest float, which is infinity for one too big for a float
+fn big_to_float(value: &BigInt) -> f64 {
+    value.to_f64().unwrap_or(f64::NAN)
+}
+
+// Arithmetic on floats follows IEEE 754 like the hardware does: it never fails, results that are too big become
+// infinity and dividing by zero gives infinity or NaN. `%` rounds down like it does for numbers, and any exponent
+// is allowed.
+fn float_math(op: &str, lhs: f64, rhs: f64, span: Span) -> Result<Value, AsaError> {
+    let result = match op {
+        "+" => lhs + rhs,
+        "-" => lhs - rhs,
+        "*" => lhs * rhs,
+        "/" => lhs / rhs,
+        "%" => {
+            let remainder = lhs % rhs;
+            if remainder != 0.0 && (remainder < 0.0) != (rhs < 0.0) { remainder + rhs } else { remainder }
+        },
+        "^" => lhs.powf(rhs),
+        _ => return Err(AsaError::UnknownOperator { operator: op.to_string(), span: Some(span) }),
+    };
+    Ok(Value::Float(result))
+}
+
+// Division rounds down, towards negative infinity, rather than towards zero: `-7 / 2` is -4 and `7 / -2` is -4.
+// Like the other `overflowing_` operations it also says whether the result wrapped around, which only happens for
+// the smallest number divided by -1. The divisor can't be 0.
+fn floor_div(lhs: i64, rhs: i64) -> (i64, bool) {
+    let (quotient, overflowed) = lhs.overflowing_div(rhs);
+    if lhs.wrapping_rem(rhs) != 0 && (lhs < 0) != (rhs < 0) {
+        (quotient - 1, overflowed)
+    } else {
+        (quotient, overflowed)
+    }
+}
+
+// The remainder that goes with `floor_div`, so `(a / b) * b + a % b == a` always holds. It has the sign of the
+// divisor: `-7 % 2` is 1 and `7 % -2` is -1. The divisor can't be 0.
+fn floor_mod(lhs: i64, rhs: i64) -> i64 {
+    // the only remainder that doesn't fit is the smallest number's by -1, which is 0 anyway
+    let remainder = lhs.wrapping_rem(rhs);
+    if remainder != 0 && (remainder < 0) != (rhs < 0) {
+        remainder + rhs
+    } else {
+        remainder
+    }
+}
+
+// `lhs ^ rhs` for an exponent of 0 or more, along with whether the result wrapped around like the other
+// `overflowing_` operations
+fn overflowing_pow(lhs: i64, rhs: i64) -> (i64, bool) {
+    if let Ok(exponent) = u32::try_from(rhs) {
+        return lhs.overflowing_pow(exponent);
+    }
+    // an exponent this big only leaves 0, 1 and -1 small enough to fit, the wrapped around result is still worked
+    // out by squaring
+    let (mut base, mut exponent, mut result) = (lhs, rhs, 1i64);
+    while exponent > 0 {
+        if exponent % 2 == 1 {
+            result = result.wrapping_mul(base);
+        }
+        base = base.wrapping_mul(base);
+        exponent /= 2;
+    }
+    (result, !(-1..=1).contains(&lhs))
+}
+
+// Compares two values of the same type, numbers by size, strings alphabetically and `false` before `true`. A number
+// compared to a big integer is turned into a big integer first, and either of them compared to a float is turned
+// into a float. NaN isn't equal to, less than or greater than anything, itself included, so every comparison with it
+// is false except `!=`.
+fn compare(op: &str, lhs: Value, rhs: Value, span: Span) -> Result<Value, AsaError> {
+    let (lhs, rhs) = match (lhs, rhs) {
+        (Value::Number(lhs), Value::BigInt(rhs)) => (Value::BigInt(BigInt::from(lhs)), Value::BigInt(rhs)),
+        (Value::BigInt(lhs), Value::Number(rhs)) => (Value::BigInt(lhs), Value::BigInt(BigInt::from(rhs))),
+        (Value::Number(lhs), Value::Float(rhs)) => (Value::Float(lhs as f64), Value::Float(rhs)),
+        (Value::Float(lhs), Value::Number(rhs)) => (Value::Float(lhs), Value::Float(rhs as f64)),
+        (Value::BigInt(lhs), Value::Float(rhs)) => (Value::Float(big_to_float(&lhs)), Value::Float(rhs)),
+        (Value::Float(lhs), Value::BigInt(rhs)) => (Value::Float(lhs), Value::Float(big_to_float(&rhs))),
+        values => values,
+    };
+    // values of different types can't be compared, not even for equality
+    if lhs.type_name() != rhs.type_name() {
+        return Err(AsaError::TypeMismatch { expected: lhs.type_name().to_string(), found: rhs, span: Some(span) });
+    }
+    match op {
+        "<" => Ok(Value::Bool(lhs < rhs)),
+        ">" => Ok(Value::Bool(lhs > rhs)),
+        "==" => Ok(Value::Bool(lhs == rhs)),
+        "!=" => Ok(Value::Bool(lhs != rhs)),
+        ">=" => Ok(Value::Bool(lhs >= rhs)),
+        "<=" => Ok(Value::Bool(lhs <= rhs)),
+        _ => Err(AsaError::UnknownOperator { operator: op.to_string(), span: Some(span) }),
+    }
+}
+
+// Functions every program can call without defining them, a function the program defines with the same name is
+// called instead
+const BUILTINS: [&str; 2] = ["int", "float"];
+
+// Runs a builtin on its argument. `float` turns a number into a float, `int` turns a float into a number by
+// dropping the fraction, which fails for NaN and infinity. Floats too big for 64 bits become big integers.
+fn builtin(name: &str, arg: Value, span: Span) -> Result<Value, AsaError> {
+    match (name, arg) {
+        ("float", Value::Number(value)) => Ok(Value::Float(value as f64)),
+        ("float", Value::BigInt(value)) => Ok(Value::Float(big_to_float(&value))),
+        ("int", value @ (Value::Number(_) | Value::BigInt(_))) => Ok(value),
+        ("float", Value::Float(value)) => Ok(Value::Float(value)),
+        ("int", Value::Float(value)) => match BigInt::from_f64(value.trunc()) {
+            Some(truncated) => Ok(Value::from(truncated)),
+            None => Err(AsaError::Conversion { found: Value::Float(value), target: "number".to_string(), span: Some(span) }),
+        },
+        ("int" | "float", found) => Err(AsaError::TypeMismatch { expected: "number".to_string(), found, span: Some(span) }),
+        _ => Err(AsaError::UndefinedFunction { name: name.to_string(), span: Some(span) }),
+    }
+}
+
+pub fn start_interpreter(node: &Node) -> Result<Value, AsaError> {  
+  Runtime::new().start(node)
 }
 
 
@@ -1,7 +1,11 @@
 extern crate nom;
 
+pub mod environment;
+pub mod error;
 pub mod interpreter;
 pub mod parser;
 
-pub use self::parser::{program, Node};
-pub use self::interpreter::{Runtime, Value, start_interpreter};
\ No newline at end of file
+pub use self::error::AsaError;
+pub use self::parser::{parse_program, program, Node, ParseError, Span};
+pub use self::interpreter::{Arithmetic, Runtime, Value, start_interpreter};
+pub use num_bigint::BigInt;
\ No newline at end of file
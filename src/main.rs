@@ -1,22 +1,294 @@
-extern crate nom;
 extern crate asalang;
 
-use asalang::{program, Runtime, Node, start_interpreter};
+use std::env;
+use std::fs;
+use std::io::{self, BufRead, Read, Write};
+use std::process;
 
-fn main() -> Result<(), nom::Err<(&'static str, nom::error::ErrorKind)>> {
-  
-  let result = program(r#"8<9"#);
-  match result {
-    Ok((unparsed,tree)) => {
-      println!("Unparsed Text: {:?}", unparsed);
-      println!("Parse Tree:\n {:#?}", tree);
-      let interpreter_result = start_interpreter(&tree);
-      println!("{:?}", interpreter_result);
+use asalang::{parse_program, Arithmetic, AsaError, Runtime, Value};
+
+const USAGE: &str = "usage: asalang [--ast] [--quiet] [--wrapping] [FILE]
+       asalang repl [--ast] [--wrapping]
+
+Runs an Asa program from FILE, or from stdin when FILE is missing or `-`.
+`repl` starts an interactive session instead.
+
+options:
+  --ast      print the parse tree before running the program
+  --quiet    don't print the value the program evaluates to
+  --wrapping let arithmetic wrap around at 64 bits instead of growing into big integers
+  --help     show this message";
+
+const PROMPT: &str = "asa> ";
+const CONTINUATION_PROMPT: &str = "...  ";
+
+// Options picked up from the command line
+struct Options {
+  repl: bool,           // start an interactive session
+  path: Option<String>, // file to run, None means stdin
+  ast: bool,            // print the parse tree
+  quiet: bool,          // don't print the resulting value
+  wrapping: bool,       // arithmetic wraps around instead of promoting to big integers on overflow
+}
+
+// Walks the command line arguments and fills in the options, returns an error message for anything it doesn't know
+fn parse_args<I: Iterator<Item = String>>(args: I) -> Result<Options, String> {
+  let mut options = Options { repl: false, path: None, ast: false, quiet: false, wrapping: false };
+  let mut args = args.peekable();
+  if args.peek().map(String::as_str) == Some("repl") {
+    args.next();
+    options.repl = true;
+  }
+  for arg in args {
+    match arg.as_str() {
+      "--ast" => options.ast = true,
+      "--quiet" | "-q" => options.quiet = true,
+      "--wrapping" => options.wrapping = true,
+      "-" => options.path = None,
+      flag if flag.starts_with('-') => return Err(format!("unknown option `{}`", flag)),
+      path => {
+        if options.repl {
+          return Err("the repl doesn't take a source file".to_string());
+        }
+        if options.path.is_some() {
+          return Err("only one source file can be given".to_string());
+        }
+        options.path = Some(path.to_string());
+      }
+    }
+  }
+  Ok(options)
+}
+
+// Reads the whole program either from the given file or from stdin
+fn read_source(path: &Option<String>) -> io::Result<String> {
+  match path {
+    Some(path) => fs::read_to_string(path),
+    None => {
+      let mut source = String::new();
+      io::stdin().read_to_string(&mut source)?;
+      Ok(source)
+    }
+  }
+}
+
+// The runtime to run programs in, with the arithmetic the options ask for
+fn runtime(options: &Options) -> Runtime {
+  Runtime::with_arithmetic(if options.wrapping { Arithmetic::Wrapping } else { Arithmetic::Promoting })
+}
+
+// Parses and runs the program, returns the exit code for the process. The value the program evaluates to is printed
+// unless it has none.
+fn run(source: &str, options: &Options) -> i32 {
+  let tree = match parse_program(source).map_err(AsaError::from) {
+    Ok(tree) => tree,
+    Err(error) => {
+      eprintln!("error: {}", error.render(source));
+      return 1;
+    }
+  };
+  if options.ast {
+    println!("{:#?}", tree);
+  }
+  match runtime(options).start(&tree) {
+    Ok(value) => {
+      if !options.quiet && value != Value::Unit {
+        println!("{}", value);
+      }
+      0
     }
     Err(error) => {
-      println!("ERROR {:?}", error);
+      eprintln!("error: {}", error.render(source));
+      1
     }
   }
-  Ok(())
 }
 
+// Counts the `{` and `(` that haven't been closed yet, skipping over string literals
+fn open_brackets(source: &str) -> i32 {
+  let mut depth = 0;
+  let mut in_string = false;
+  for c in source.chars() {
+    match c {
+      '"' => in_string = !in_string,
+      '{' | '(' if !in_string => depth += 1,
+      '}' | ')' if !in_string => depth -= 1,
+      _ => (),
+    }
+  }
+  depth
+}
+
+// The whole words of `source` outside of string literals, along with where each one starts
+fn words(source: &str) -> Vec<(usize, &str)> {
+  let mut words = vec![];
+  let mut in_string = false;
+  let mut word_start = None;
+  for (ix, c) in source.char_indices().chain(std::iter::once((source.len(), ' '))) {
+    if !in_string && (c.is_alphanumeric() || c == '_') {
+      word_start.get_or_insert(ix);
+      continue;
+    }
+    if let Some(start) = word_start.take() {
+      words.push((start, &source[start..ix]));
+    }
+    if c == '"' {
+      in_string = !in_string;
+    }
+  }
+  words
+}
+
+// Where the last `else` keyword starts, skipping over string literals and names that only contain the word
+fn last_else(source: &str) -> Option<usize> {
+  words(source).into_iter().rev().find(|(_, word)| *word == "else").map(|(ix, _)| ix)
+}
+
+// Whether the line carries on an if chain from the lines before it, which it does when it starts with `else`
+fn starts_with_else(line: &str) -> bool {
+  words(line.trim_start()).first() == Some(&(0, "else"))
+}
+
+// An if chain is finished once its last block is a plain `else`, until then another `else` may still follow
+fn ends_with_else(source: &str) -> bool {
+  match last_else(source) {
+    Some(ix) => source[ix + "else".len()..].trim_start().starts_with('{'),
+    None => false,
+  }
+}
+
+// Parses and runs one piece of REPL input against the session's runtime, echoing the value it produced
+fn eval_input(runtime: &mut Runtime, source: &str, options: &Options) {
+  let tree = match parse_program(source).map_err(AsaError::from) {
+    Ok(tree) => tree,
+    Err(error) => {
+      eprintln!("error: {}", error.render(source));
+      return;
+    }
+  };
+  if options.ast {
+    println!("{:#?}", tree);
+  }
+  match runtime.eval(&tree) {
+    // statements without a value, like defining a function or a loop, have nothing worth echoing
+    Ok(Value::Unit) => (),
+    Ok(value) => println!("{}", value),
+    Err(error) => eprintln!("error: {}", error.render(source)),
+  }
+}
+
+// Reads programs line by line and runs them in one runtime, so functions and variables carry over between lines.
+// Input is collected over several lines while brackets are still open, and a finished `if` block waits for the next
+// line in case it starts with `else`.
+fn repl(options: &Options) -> i32 {
+  let mut runtime = runtime(options);
+  let stdin = io::stdin();
+  let mut lines = stdin.lock().lines();
+  let mut buffer = String::new();
+  let mut pending_if = false;
+  loop {
+    print!("{}", if buffer.is_empty() { PROMPT } else { CONTINUATION_PROMPT });
+    io::stdout().flush().ok();
+    let line = match lines.next() {
+      Some(Ok(line)) => line,
+      Some(Err(error)) => {
+        eprintln!("error: {}", error);
+        return 1;
+      }
+      None => {
+        if !buffer.trim().is_empty() {
+          println!();
+          eval_input(&mut runtime, &buffer, options);
+        }
+        println!();
+        return 0;
+      }
+    };
+    // the if chain waiting for an else is over, run it before looking at the new line
+    if pending_if && !starts_with_else(&line) {
+      eval_input(&mut runtime, &buffer, options);
+      buffer.clear();
+    }
+    pending_if = false;
+    if buffer.is_empty() && line.trim().is_empty() {
+      continue;
+    }
+    buffer.push_str(&line);
+    buffer.push('\n');
+    if open_brackets(&buffer) > 0 {
+      continue;
+    }
+    if buffer.trim_start().starts_with("if ") && !ends_with_else(&buffer) {
+      pending_if = true;
+      continue;
+    }
+    eval_input(&mut runtime, &buffer, options);
+    buffer.clear();
+  }
+}
+
+fn main() {
+  let args: Vec<String> = env::args().skip(1).collect();
+  if args.iter().any(|arg| arg == "--help" || arg == "-h") {
+    println!("{}", USAGE);
+    return;
+  }
+  let options = match parse_args(args.into_iter()) {
+    Ok(options) => options,
+    Err(message) => {
+      eprintln!("error: {}\n\n{}", message, USAGE);
+      process::exit(2);
+    }
+  };
+  if options.repl {
+    process::exit(repl(&options));
+  }
+  let source = match read_source(&options.path) {
+    Ok(source) => source,
+    Err(error) => {
+      eprintln!("error: could not read {}: {}", options.path.as_deref().unwrap_or("stdin"), error);
+      process::exit(1);
+    }
+  };
+  process::exit(run(&source, &options));
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn open_brackets_skips_strings() {
+    assert_eq!(open_brackets("fn main() {"), 1);
+    assert_eq!(open_brackets("f(g(1)"), 1);
+    assert_eq!(open_brackets("if a { 1 } else { 2 }"), 0);
+    assert_eq!(open_brackets("let s = \"a { b\";"), 0);
+    assert_eq!(open_brackets("}"), -1);
+  }
+
+  #[test]
+  fn last_else_finds_whole_words() {
+    assert_eq!(last_else("if a { 1 } else { 2 }"), Some(11));
+    assert_eq!(last_else("if a { 1 } else if b { 2 } else { 3 }"), Some(27));
+    assert_eq!(last_else("if a { elsewhere }"), None);
+    assert_eq!(last_else("if a { \"else\" }"), None);
+    assert_eq!(last_else("if a { 1 } else { elsewhere }"), Some(11));
+  }
+
+  #[test]
+  fn ends_with_else_needs_a_plain_else_block() {
+    assert!(ends_with_else("if a { 1 } else { 2 }"));
+    assert!(ends_with_else("if a { 1 } else { elsewhere }"));
+    assert!(!ends_with_else("if a { 1 } else if b { 2 }"));
+    assert!(!ends_with_else("if a { 1 }"));
+    assert!(!ends_with_else("if a { \"else\" }"));
+  }
+
+  #[test]
+  fn starts_with_else_needs_the_whole_word() {
+    assert!(starts_with_else("else { 2 }"));
+    assert!(starts_with_else("  else if b { 2 }"));
+    assert!(!starts_with_else("elsewhere = 3;"));
+    assert!(!starts_with_else("x = else;"));
+  }
+}
@@ -4,377 +4,818 @@
 use nom::{
     IResult,
     branch::alt,
-    combinator::opt,
+    combinator::{cut, not, opt, peek, recognize},
+    sequence::{pair, preceded, terminated, tuple},
+    error::{context, ContextError, ErrorKind},
     multi::{many1, many0},
     bytes::complete::{tag},
-    character::complete::{alphanumeric1, digit1},
+    character::complete::{alphanumeric1, digit1, multispace0},
   };
+  use nom_locate::LocatedSpan;
+  use num_bigint::BigInt;
+  use std::fmt;
+
+  // The parsers work on the source text wrapped in a LocatedSpan, which keeps track of the offset, line and column
+  // of whatever is left to parse so every node can record where it came from.
+  pub type Input<'a> = LocatedSpan<&'a str>;
+
+  // Where a node came from in the source: the byte range it covers and the line/column it starts at (both 1-based)
+  #[derive(Debug, Clone, Copy, PartialEq, Default)]
+  pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub line: u32,
+    pub column: usize,
+  }
+
+  impl Span {
+    // The span of everything consumed between `start` and `end`, where both are inputs seen during parsing
+    pub fn between(start: Input, end: Input) -> Span {
+      Span {
+        start: start.location_offset(),
+        end: end.location_offset(),
+        line: start.location_line(),
+        column: start.get_utf8_column(),
+      }
+    }
+
+    // Stretches this span so it runs up to the end of `other`
+    pub fn to(&self, other: &Span) -> Span {
+      Span { end: other.end, ..*self }
+    }
+
+    // Shows the source line the span starts on, with carets under the part the span covers
+    pub fn underline(&self, source: &str) -> String {
+      let text = source.lines().nth(self.line as usize - 1).unwrap_or("");
+      let gutter = " ".repeat(self.line.to_string().len());
+      let indent = text.chars().take(self.column - 1).map(|c| if c == '\t' { '\t' } else { ' ' }).collect::<String>();
+      // only underline up to the end of the first line for spans that cover several
+      let available = text.chars().count().saturating_sub(self.column - 1);
+      let width = source.get(self.start..self.end).map_or(1, |covered| covered.chars().count()).min(available).max(1);
+      format!("{} |\n{} | {}\n{} | {}{}", gutter, self.line, text, gutter, indent, "^".repeat(width))
+    }
+  }
+
+  // Prints the span as line:column
+  impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+      write!(f, "{}:{}", self.line, self.column)
+    }
+  }
+
+  // A parse failure: where it happened, what the parser was looking for there and what it found instead
+  #[derive(Debug, Clone, PartialEq)]
+  pub struct ParseError {
+    pub span: Span,
+    pub expected: String,
+    pub found: String,
+  }
+
+  impl ParseError {
+    // An error at the start of `input` saying `expected` should have been there
+    pub fn expected(input: Input, expected: String) -> ParseError {
+      // whitespace is never what's wrong, so the error points at whatever comes after it
+      let (input, _) = multispace0::<_, nom::error::Error<Input>>(input).unwrap_or((input, input));
+      // what was found is the next word or symbol, so the caret underlines something the reader can recognise
+      let rest = input.fragment().trim_end();
+      let length = match rest.chars().next() {
+        None => 0,
+        Some(c) if c.is_alphanumeric() => rest.find(|c: char| !c.is_alphanumeric()).unwrap_or(rest.len()),
+        Some(c) => c.len_utf8(),
+      };
+      let found = if length == 0 { "end of input".to_string() } else { format!("`{}`", &rest[..length]) };
+      let mut span = Span::between(input, input);
+      span.end += length;
+      ParseError { span, expected, found }
+    }
+  }
+
+  impl<'a> nom::error::ParseError<Input<'a>> for ParseError {
+    // Errors straight from nom only know which kind of parser failed, the grammar rules add better descriptions with context
+    fn from_error_kind(input: Input<'a>, kind: ErrorKind) -> Self {
+      let expected = match kind {
+        ErrorKind::AlphaNumeric => "a name",
+        ErrorKind::Digit => "a number",
+        ErrorKind::Tag | ErrorKind::Char => "a keyword or symbol",
+        ErrorKind::Not => "a different symbol",
+        _ => "more of the program",
+      };
+      ParseError::expected(input, expected.to_string())
+    }
+
+    fn append(_: Input<'a>, _: ErrorKind, other: Self) -> Self {
+      other
+    }
+
+    // When every alternative fails, report the one that got furthest since it's the closest to what was meant
+    fn or(self, other: Self) -> Self {
+      if other.span.start >= self.span.start { other } else { self }
+    }
+  }
+
+  impl<'a> ContextError<Input<'a>> for ParseError {
+    // The context names the whole rule, which describes the problem better than whatever failed first inside it,
+    // but only as long as the rule didn't get anywhere before failing
+    fn add_context(input: Input<'a>, context: &'static str, other: Self) -> Self {
+      let here = ParseError::expected(input, context.to_string());
+      if other.span.start <= here.span.start { here } else { other }
+    }
+  }
+
+  // Prints the error as `line:column: expected ..., found ...`
+  impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+      write!(f, "{}: expected {}, found {}", self.span, self.expected, self.found)
+    }
+  }
+
+  // Matches `text` like tag, but commits to the rule it's in: if `text` is missing the error says it was expected
+  fn expect<'a>(text: &'static str) -> impl FnMut(Input<'a>) -> IResult<Input<'a>, Input<'a>, ParseError> {
+    move |input| cut(tag::<_, _, ParseError>(text))(input).map_err(|error| error.map(|_| ParseError::expected(input, format!("`{}`", text))))
+  }
+
+  // Matches the `closing` delimiter of `what`, pointing back at the `opening` one when it's missing
+  fn closing<'a>(closing: &'static str, opening: Input<'a>, what: String) -> impl FnMut(Input<'a>) -> IResult<Input<'a>, Input<'a>, ParseError> {
+    move |input| cut(tag::<_, _, ParseError>(closing))(input).map_err(|error| error.map(|_| {
+      ParseError::expected(input, format!("`{}` to close {} opened at {}", closing, what, Span::between(opening, opening)))
+    }))
+  }
+
   // Here are the different node types used in parser and grammar
   #[derive(Debug, Clone)]
   pub enum Node {
-    Program { children: Vec<Node> },
-    Statement { children: Vec<Node> },
-    FunctionReturn { children: Vec<Node> },
-    FunctionDefine { children: Vec<Node> },
-    FunctionArguments { children: Vec<Node> },
-    FunctionStatements { children: Vec<Node> },
-    Expression { children: Vec<Node> },
-    MathExpression {name: String, children: Vec<Node> },
-    FunctionCall { name: String, children: Vec<Node> },
-    VariableDefine { children: Vec<Node> },
-    Number { value: i32 },
-    Bool { value: bool },
-    Identifier { value: String },
-    String { value: String },
-    ConditionalOperator { value: String },
-    ConditionalValue { children: Vec<Node> },
-    ConditionalExpression { children: Vec<Node> },
-    ConditionalExpressionMultiple { children: Vec<Node> },
-    IfStatement{ children: Vec<Node> },
-    ElseStatement{ children: Vec<Node> },
-    ElseIfStatement{ children: Vec<Node> },
-    IfElseStatements{ children: Vec<Node> },
+    Program { children: Vec<Node>, span: Span },
+    Statement { children: Vec<Node>, span: Span },
+    FunctionReturn { children: Vec<Node>, span: Span },
+    FunctionDefine { children: Vec<Node>, span: Span },
+    FunctionArguments { children: Vec<Node>, span: Span },
+    FunctionStatements { children: Vec<Node>, span: Span },
+    Expression { children: Vec<Node>, span: Span },
+    MathExpression {name: String, children: Vec<Node>, span: Span },
+    FunctionCall { name: String, children: Vec<Node>, span: Span },
+    VariableDefine { children: Vec<Node>, span: Span },
+    Assignment { name: String, children: Vec<Node>, span: Span },
+    Number { value: i64, span: Span },
+    BigInt { value: BigInt, span: Span }, // a number literal too big for 64 bits
+    Float { value: f64, span: Span },
+    Bool { value: bool, span: Span },
+    Identifier { value: String, span: Span },
+    String { value: String, span: Span },
+    ConditionalExpression { name: String, children: Vec<Node>, span: Span },
+    ConditionalExpressionMultiple { children: Vec<Node>, span: Span },
+    LogicalExpression { name: String, children: Vec<Node>, span: Span },
+    UnaryExpression { name: String, children: Vec<Node>, span: Span },
+    IfStatement{ children: Vec<Node>, span: Span },
+    ElseStatement{ children: Vec<Node>, span: Span },
+    ElseIfStatement{ children: Vec<Node>, span: Span },
+    IfElseStatements{ children: Vec<Node>, span: Span },
+    WhileStatement{ children: Vec<Node>, span: Span },
+    ForStatement{ children: Vec<Node>, span: Span },
+    Range{ name: String, children: Vec<Node>, span: Span },
+    BreakStatement{ span: Span },
+    ContinueStatement{ span: Span },
+  }
 
+  impl Node {
+    // Where in the source this node was parsed from
+    pub fn span(&self) -> Span {
+      match self {
+        Node::Program { span, .. } |
+        Node::Statement { span, .. } |
+        Node::FunctionReturn { span, .. } |
+        Node::FunctionDefine { span, .. } |
+        Node::FunctionArguments { span, .. } |
+        Node::FunctionStatements { span, .. } |
+        Node::Expression { span, .. } |
+        Node::MathExpression { span, .. } |
+        Node::FunctionCall { span, .. } |
+        Node::VariableDefine { span, .. } |
+        Node::Assignment { span, .. } |
+        Node::Number { span, .. } |
+        Node::BigInt { span, .. } |
+        Node::Float { span, .. } |
+        Node::Bool { span, .. } |
+        Node::Identifier { span, .. } |
+        Node::String { span, .. } |
+        Node::ConditionalExpression { span, .. } |
+        Node::ConditionalExpressionMultiple { span, .. } |
+        Node::LogicalExpression { span, .. } |
+        Node::UnaryExpression { span, .. } |
+        Node::IfStatement { span, .. } |
+        Node::ElseStatement { span, .. } |
+        Node::ElseIfStatement { span, .. } |
+        Node::IfElseStatements { span, .. } |
+        Node::WhileStatement { span, .. } |
+        Node::ForStatement { span, .. } |
+        Node::Range { span, .. } |
+        Node::BreakStatement { span } |
+        Node::ContinueStatement { span } => *span,
+      }
+    }
   }
   
-  // identifier = {alnum} ;
-  pub fn identifier(input: &str) -> IResult<&str, Node> {
+  // Words that start a statement or a block, or that are a value of their own, so they can't be used as names
+  pub const KEYWORDS: [&str; 12] = ["let", "fn", "return", "if", "else", "while", "for", "in", "break", "continue", "true", "false"];
+
+  // identifier = {alnum} - keyword ;
+  pub fn identifier(input: Input) -> IResult<Input, Node, ParseError> {
+    let start = input;
     let (input, result) = alphanumeric1(input)?;              // Consume at least 1 alphanumeric character. The ? automatically unwraps the result if it's okay and bails if it is an error.
-    Ok((input, Node::Identifier{ value: result.to_string()})) // Return the now partially consumed input, as well as a node with the string on it.
+    if KEYWORDS.contains(result.fragment()) {
+      return Err(nom::Err::Error(ParseError::expected(start, "a name".to_string()))); // a keyword is never a name, this lets the parsers that expect one try something else
+    }
+    Ok((input, Node::Identifier{ value: result.to_string(), span: Span::between(result, input)})) // Return the now partially consumed input, as well as a node with the string on it.
   }
   
-  // number = {digit} ;
-  pub fn number(input: &str) -> IResult<&str, Node> {
-    let (input, result) = digit1(input)?;                     // Consume at least 1 digit 0-9
-    let number = result.parse::<i32>().unwrap();              // Parse the string result into a usize
-    Ok((input, Node::Number{ value: number}))                 // Return the now partially consumed input with a number as well
+  // number = ["-"], {digit} ;
+  pub fn number(input: Input) -> IResult<Input, Node, ParseError> {
+    let start = input;
+    let (input, result) = recognize(pair(opt(tag("-")), digit1))(input)?; // Consume at least 1 digit 0-9, with a "-" right in front the literal is negative so the smallest number can be written
+    if let Ok(value) = result.parse::<i64>() {                // Parse the string result into an i64
+      return Ok((input, Node::Number{ value, span: Span::between(result, input)})) // Return the now partially consumed input with a number as well
+    }
+    match result.parse::<BigInt>() {                          // Too many digits for 64 bits, so keep the literal as a big integer
+      Ok(value) => Ok((input, Node::BigInt{ value, span: Span::between(result, input)})),
+      Err(_) => Err(nom::Err::Error(ParseError::expected(start, "a number".to_string()))),
+    }
+  }
+
+  // float = ["-"], {digit}, ((".", {digit}, [exponent]) | exponent) ;
+  // A float needs digits on both sides of the ".", so `0..3` is still a range
+  pub fn float(input: Input) -> IResult<Input, Node, ParseError> {
+    let start = input;
+    let (input, result) = recognize(tuple((opt(tag("-")), digit1, alt((recognize(pair(pair(tag("."), digit1), opt(exponent))), exponent)))))(input)?; // Consume the digits, then either a fraction with an optional exponent or just an exponent
+    match result.parse::<f64>() { // Parse the string result into a f64
+      Ok(value) => Ok((input, Node::Float{ value, span: Span::between(result, input)})),
+      Err(_) => Err(nom::Err::Error(ParseError::expected(start, "a float".to_string()))),
+    }
+  }
+
+  // exponent = ("e" | "E"), ["+" | "-"], {digit} ;
+  pub fn exponent(input: Input) -> IResult<Input, Input, ParseError> {
+    recognize(tuple((alt((tag("e"), tag("E"))), opt(alt((tag("+"), tag("-")))), digit1)))(input) // recognize returns everything the exponent's parts consumed
   }
 
   // boolean  = "true" | "false" ;
-  pub fn boolean(input: &str) -> IResult<&str, Node> {
-    let (input, result) = alt((tag("true"),tag("false")))(input)?; // takes true and false as a list of tags and returns whichever tag the parser recognizes in input 
-    let bool_value = if result == "true" {true} else {false}; // uses match statement to return the boolean output depending on tag 
-    Ok((input, Node::Bool{ value: bool_value}))
+  pub fn boolean(input: Input) -> IResult<Input, Node, ParseError> {
+    let (input, result) = terminated(alt((tag("true"),tag("false"))), not(alphanumeric1))(input)?; // takes true and false as a list of tags and returns whichever tag the parser recognizes in input, as long as it isn't the start of a longer name
+    let bool_value = *result.fragment() == "true"; // compares the recognized tag to get the boolean output 
+    Ok((input, Node::Bool{ value: bool_value, span: Span::between(result, input)}))
   }
 
   // string  = "\"" , {alnum | " "} , "\"" ;
-  pub fn string(input: &str) -> IResult<&str, Node> {
-    let (input, _) = tag("\"")(input)?; // tag recognizes "\" and consumes it and returns partially consumed input in input
-    let (input, string) = many1(alt((alphanumeric1,tag(" "))))(input)?; // applies parser 1 or more times while applies alt to check for alphanumeric characters or space " " tag and if there's alphanum or space it consumes that and returns the now partially consumed input in input
-    let (input, _) = tag("\"")(input)?; // tag recognizes "\" and consumes it and returns partially consumed input in input
-    Ok((input, Node::String{ value: string.join("")})) // Return the now partially consumed input, as well as a node with the string on it.
+  pub fn string(input: Input) -> IResult<Input, Node, ParseError> {
+    let start = input;
+    let (input, open) = tag("\"")(input)?; // tag recognizes "\" and consumes it and returns partially consumed input in input, from here on it can only be a string
+    let (input, string) = cut(context("a letter, digit or space", many1(alt((alphanumeric1,tag(" "))))))(input)?; // applies parser 1 or more times while applies alt to check for alphanumeric characters or space " " tag and if there's alphanum or space it consumes that and returns the now partially consumed input in input
+    let (input, _) = closing("\"", open, "the string".to_string())(input)?; // closing recognizes "\" and consumes it and returns partially consumed input in input
+    let value = string.iter().map(|part| *part.fragment()).collect(); // glues the consumed pieces back together
+    Ok((input, Node::String{ value, span: Span::between(start, input)})) // Return the now partially consumed input, as well as a node with the string on it.
   }
 
   // function_call  = identifier , "(" , [arguments] , ")" ;
-  pub fn function_call(input: &str) -> IResult<&str, Node> {
+  pub fn function_call(input: Input) -> IResult<Input, Node, ParseError> {
+    let start = input;
     let (input, name) = alphanumeric1(input)?; // Consumes at least 1 alphanumeric character and returns in name 
-    let (input, _) = tag("(")(input)?; // tag recognizes "(" and consumes it and returns partially consumed input in input
-    let (input, mut args) = many0(arguments)(input)?; // applies parser 0 or more times to recognise arguments function and returns in args 
-    let (input, _) = tag(")")(input)?; // tag recognizes ")" and consumes it and returns partially consumed input in input
-    Ok((input, Node::FunctionCall{name: name.to_string(), children: args}))   
+    let (input, open) = tag("(")(input)
+      .map_err(|error: nom::Err<ParseError>| error.map(|_| ParseError::expected(start, "a function call".to_string())))?; // tag recognizes "(" and consumes it and returns partially consumed input in input. Without one it was never a call, so the error points back at the name
+    let (input, args) = many0(arguments)(input)?; // applies parser 0 or more times to recognise arguments function and returns in args 
+    let (input, _) = closing(")", open, format!("the call to `{}`", name))(input)?; // once there's a "(" this can only be a call, so the ")" has to follow
+    Ok((input, Node::FunctionCall{name: name.to_string(), children: args, span: Span::between(name, input)}))   
   }
 
-  // parenthetical_expression = "(" , l1, ")" ;
-  pub fn parenthetical_expression(input: &str) -> IResult<&str, Node> {
+  // parenthetical_expression = "(" , expression, ")" ;
+  pub fn parenthetical_expression(input: Input) -> IResult<Input, Node, ParseError> {
     let (input, _) = many0(tag(" "))(input)?; // many0 applies parser 0 or more times to check for space " " and if there is a space, it consumes that space and returns the now partially consumed input in input
-    let (input, _) = tag("(")(input)?;  // tag recognizes "(" and consumes it and returns partially consumed input in input
+    let (input, open) = tag("(")(input)?;  // tag recognizes "(" and consumes it and returns partially consumed input in input
     let (input, _) = many0(tag(" "))(input)?; // many0 applies parser 0 or more times to check for space " " and if there is a space, it consumes that space and returns the now partially consumed input in input
-    let (input, args) = l1(input)?; // calls l1 function which returns the output of the function in args as well as the now partially consumed input in input
+    let (input, args) = cut(context("an expression", expression))(input)?; // calls expression function which returns the output of the function in args as well as the now partially consumed input in input, so a condition or an if can be grouped as well
+    // an if keeps the `Expression` around it, which is what runs it as a value, anything else is grouped as it is
+    let args = match args {
+      Node::Expression{mut children, ..} if !matches!(children[0], Node::IfElseStatements{..}) => children.remove(0),
+      args => args,
+    };
     let (input, _) = many0(tag(" "))(input)?; // many0 applies parser 0 or more times to check for space " " and if there is a space, it consumes that space and returns the now partially consumed input in input
-    let (input, _) = tag(")")(input)?;  // tag recognizes ")" and consumes it and returns partially consumed input in input
+    let (input, _) = closing(")", open, "the parenthesis".to_string())(input)?;  // closing recognizes ")" and consumes it and returns partially consumed input in input
     let (input, _) = many0(tag(" "))(input)?; // many0 applies parser 0 or more times to check for space " " and if there is a space, it consumes that space and returns the now partially consumed input in input
     Ok((input, args))
   }
 
-  // l4 = (function_call | number | identifier | parenthetical_expression) ;
-  pub fn l4(input: &str) -> IResult<&str, Node> {
-    alt((function_call, number, identifier, parenthetical_expression))(input) // takes function_call, number, identifier, parenthetical_expression as a list of functions and returns whichever function the parser recognizes in input 
+  // l4 = (function_call | float | number | boolean | string | identifier | parenthetical_expression) ;
+  pub fn l4(input: Input) -> IResult<Input, Node, ParseError> {
+    alt((function_call, float, number, boolean, string, identifier, parenthetical_expression))(input) // takes function_call, float, number, boolean, string, identifier, parenthetical_expression as a list of functions and returns whichever function the parser recognizes in input, a float goes before number so its digits aren't read as a whole number and a boolean goes before identifier so true and false aren't read as names
   }
 
-  // l3_infix = "^", l4 ; 
-  pub fn l3_infix(input: &str) -> IResult<&str, Node> {
+  // l3_infix = "^", l3 ; 
+  pub fn l3_infix(input: Input) -> IResult<Input, Node, ParseError> {
     let (input, _) = many0(tag(" "))(input)?; // many0 applies parser 0 or more times to check for space " " and if there is a space, it consumes that space and returns the now partially consumed input in input
     let (input, op) = tag("^")(input)?;  // tag recognizes "^" and consumes it and returns partially consumed input in input
     let (input, _) = many0(tag(" "))(input)?; // many0 applies parser 0 or more times to check for space " " and if there is a space, it consumes that space and returns the now partially consumed input in input
-    let (input, args) = l4(input)?; // calls l4 function which returns the output of the function in args as well as the now partially consumed input in input
-    Ok((input, Node::MathExpression{name: op.to_string(), children: vec![args]}))
+    let (input, args) = cut(context("a value", l3))(input)?; // calls l3 function which returns the output of the function in args as well as the now partially consumed input in input, the exponent takes in any further "^" so they group to the right
+    let span = Span::between(op, input);
+    Ok((input, Node::MathExpression{name: op.to_string(), children: vec![args], span}))
   }
 
-  // l3 = l4, [l3_infix] ; 
-  pub fn l3(input: &str) -> IResult<&str, Node> {
-    let (input, mut head) = l4(input)?; // calls l4 function which returns the output of the function in args as well as the now partially consumed input in input
-    let (input, tail) = many0(l3_infix)(input)?; // applies parser 0 or more times to recognise l3_infix function and returns in tail 
-    for n in tail { // loops through each element n in tail and matches to math expression node
-      match n {
-        Node::MathExpression{name, mut children} => {
-          let mut new_children = vec![head.clone()];
-          new_children.append(&mut children);
-          head = Node::MathExpression{name, children: new_children};
-        }
-        _ => () 
-      };
+  // unary = l4 | signed ;
+  // A sign binds tighter than any infix operator, so `-2 ^ 2` is `(-2) ^ 2`
+  pub fn unary(input: Input) -> IResult<Input, Node, ParseError> {
+    alt((l4, signed))(input) // takes l4, signed as a list of functions and returns whichever function the parser recognizes in input, a "-" right in front of digits is already part of a negative number in l4
+  }
+
+  // signed = ("-" | "+"), unary ;
+  pub fn signed(input: Input) -> IResult<Input, Node, ParseError> {
+    let (input, op) = alt((tag("-"), tag("+")))(input)?; // takes - and + as a list of tags and returns whichever tag the parser recognizes in input
+    let (input, _) = many0(tag(" "))(input)?; // many0 applies parser 0 or more times to check for space " " and if there is a space, it consumes that space and returns the now partially consumed input in input
+    let (input, operand) = cut(context("a value", unary))(input)?; // calls unary function which returns the output of the function in operand as well as the now partially consumed input in input
+    let span = Span::between(op, input);
+    Ok((input, Node::UnaryExpression{name: op.to_string(), children: vec![operand], span}))
+  }
+
+  // l3 = unary, [l3_infix] ; 
+  // `^` is right associative, `2 ^ 3 ^ 2` is `2 ^ (3 ^ 2)`
+  pub fn l3(input: Input) -> IResult<Input, Node, ParseError> {
+    let (input, head) = unary(input)?; // calls unary function which returns the output of the function in head as well as the now partially consumed input in input
+    let (input, tail) = opt(l3_infix)(input)?; // applies parser at most once to recognise l3_infix function and returns in tail, which has already taken the rest of the chain
+    match tail {
+      Some(Node::MathExpression{name, mut children, span}) => {
+        let span = head.span().to(&span); // the expression runs from the start of head to the end of the exponent
+        let mut new_children = vec![head];
+        new_children.append(&mut children);
+        Ok((input, Node::MathExpression{name, children: new_children, span}))
+      },
+      _ => Ok((input, head)),
     }
-    Ok((input, head))
   }
 
-  // l2_infix = ("*" | "/"), l2 ;
-  pub fn l2_infix(input: &str) -> IResult<&str, Node> {
+  // l2_infix = ("*" | "/" | "%"), l3 ;
+  pub fn l2_infix(input: Input) -> IResult<Input, Node, ParseError> {
     let (input, _) = many0(tag(" "))(input)?; // many0 applies parser 0 or more times to check for space " " and if there is a space, it consumes that space and returns the now partially consumed input in input
-    let (input, op) = alt((tag("*"),tag("/")))(input)?; // takes * and / as a list of tags and returns whichever tag the parser recognizes in input 
+    let (input, op) = terminated(alt((tag("*"),tag("/"),tag("%"))), not(tag("=")))(input)?; // takes *, / and % as a list of tags and returns whichever tag the parser recognizes in input, as long as it isn't the start of "*=", "/=" or "%="
     let (input, _) = many0(tag(" "))(input)?; // many0 applies parser 0 or more times to check for space " " and if there is a space, it consumes that space and returns the now partially consumed input in input
-    let (input, args) = l2(input)?; // calls l2 function which returns the output of the function in args as well as the now partially consumed input in input
-    Ok((input, Node::MathExpression{name: op.to_string(), children: vec![args]}))
+    let (input, args) = cut(context("a value", l3))(input)?; // calls l3 function which returns the output of the function in args as well as the now partially consumed input in input, only one operand so l2 can group them to the left
+    let span = Span::between(op, input);
+    Ok((input, Node::MathExpression{name: op.to_string(), children: vec![args], span}))
   }
 
-  // l2 = l3, [l2_infix] ; 
-  pub fn l2(input: &str) -> IResult<&str, Node> {
+  // l2 = l3, [{l2_infix}] ; 
+  // `*`, `/` and `%` are left associative, `8 / 4 / 2` is `(8 / 4) / 2`
+  pub fn l2(input: Input) -> IResult<Input, Node, ParseError> {
     let (input, mut head) = l3(input)?; // calls l3 function which returns the output of the function in head as well as the now partially consumed input in input
     let (input, tail) = many0(l2_infix)(input)?; // applies parser 0 or more times to recognise l2_infix function and returns in tail 
     for n in tail { // loops through each element n in tail and matches to math expression node
-      match n {
-        Node::MathExpression{name, mut children} => {
-          let mut new_children = vec![head.clone()];
-          new_children.append(&mut children);
-          head = Node::MathExpression{name, children: new_children};
-        }
-        _ => () 
-      };
+      if let Node::MathExpression{name, mut children, span} = n {
+        let span = head.span().to(&span); // the expression runs from the start of head to the end of this operand
+        let mut new_children = vec![head.clone()];
+        new_children.append(&mut children);
+        head = Node::MathExpression{name, children: new_children, span};
+      }
     }
     Ok((input, head))
   }
 
   // l1_infix = ("+" | "-"), l2 ;
-  pub fn l1_infix(input: &str) -> IResult<&str, Node> {
+  pub fn l1_infix(input: Input) -> IResult<Input, Node, ParseError> {
     let (input, _) = many0(tag(" "))(input)?; // many0 applies parser 0 or more times to check for space " " and if there is a space, it consumes that space and returns the now partially consumed input in input
-    let (input, op) = alt((tag("+"),tag("-")))(input)?; // takes + and - as a list of tags and returns whichever tag the parser recognizes in input 
+    let (input, op) = terminated(alt((tag("+"),tag("-"))), not(tag("=")))(input)?; // takes + and - as a list of tags and returns whichever tag the parser recognizes in input, as long as it isn't the start of "+=" or "-="
     let (input, _) = many0(tag(" "))(input)?; // many0 applies parser 0 or more times to check for space " " and if there is a space, it consumes that space and returns the now partially consumed input in input
-    let (input, args) = l2(input)?; // calls l2 function which returns the output of the function in args as well as the now partially consumed input in input
-    Ok((input, Node::MathExpression{name: op.to_string(), children: vec![args]}))
+    let (input, args) = cut(context("a value", l2))(input)?; // calls l2 function which returns the output of the function in args as well as the now partially consumed input in input, once there's an operator a value has to follow
+    let span = Span::between(op, input);
+    Ok((input, Node::MathExpression{name: op.to_string(), children: vec![args], span}))
   }
 
-  // l1 = l2, [l1_infix] ;
-  pub fn l1(input: &str) -> IResult<&str, Node> {
+  // l1 = l2, [{l1_infix}] ;
+  // `+` and `-` are left associative, `1 - 2 - 3` is `(1 - 2) - 3`
+  pub fn l1(input: Input) -> IResult<Input, Node, ParseError> {
     let (input, mut head) = l2(input)?; // calls l2 function which returns the output of the function in head as well as the now partially consumed input in input
     let (input, tail) = many0(l1_infix)(input)?;  // applies parser 0 or more times to recognise l1_infix function and returns in tail 
     for n in tail { // loops through each element n in tail and matches to math expression node
-      match n {
-        Node::MathExpression{name, mut children} => {
-          let mut new_children = vec![head.clone()];
-          new_children.append(&mut children);
-          head = Node::MathExpression{name, children: new_children};
-        }
-        _ => () 
-      };
+      if let Node::MathExpression{name, mut children, span} = n {
+        let span = head.span().to(&span); // the expression runs from the start of head to the end of this operand
+        let mut new_children = vec![head.clone()];
+        new_children.append(&mut children);
+        head = Node::MathExpression{name, children: new_children, span};
+      }
     }
     Ok((input, head))
   }
 
   // math_expression = l1 ; 
-  pub fn math_expression(input: &str) -> IResult<&str, Node> {
+  pub fn math_expression(input: Input) -> IResult<Input, Node, ParseError> {
     l1(input) // calls l1 function 
   }
 
-  // expression = boolean | math_expression | function_call | string ;
-  pub fn expression(input: &str) -> IResult<&str, Node> {
-    let (input, result) = alt((boolean, math_expression, function_call, string))(input)?; // takes boolean, math_expression, function_call, string as a list of functions and returns whichever function the parser recognizes in input
-    Ok((input, Node::Expression{ children: vec![result]}))   
+  // expression = if_expression | logical_exp ;
+  // Everything that has a value goes through the one chain of precedence levels, from loosest to tightest:
+  // `||`, `&&`, `!`, comparisons, `+ -`, `* /`, `^`, and then single values and parentheses.
+  pub fn expression(input: Input) -> IResult<Input, Node, ParseError> {
+    let (input, result) = alt((if_expression, logical_exp))(input)?; // takes if_expression, logical_exp as a list of functions and returns whichever function the parser recognizes in input
+    let span = result.span();
+    Ok((input, Node::Expression{ children: vec![result], span}))   
+  }
+
+  // statement  = (variable_define | assignment) , ";" ;
+  pub fn statement(input: Input) -> IResult<Input, Node, ParseError> {
+    let (input, _) = many0(alt((tag(" "),tag("\t"))))(input)?; // applies parser 0 or more times to recognise space or tab tags 
+    let (input, result) = alt((variable_define, assignment))(input)?; // calls variable_define or assignment function which returns the output of the function in result as well as the now partially consumed input in input
+    let (input, semicolon) = tag(";")(input)
+      .map_err(|error: nom::Err<ParseError>| error.map(|_| ParseError::expected(input, "`;`".to_string())))?; // tag recognizes ";" and consumes it and returns partially consumed input in input
+    let span = result.span().to(&Span::between(semicolon, input));
+    let (input, _) = many0(tag(" "))(input)?; // many0 applies parser 0 or more times to check for space " " and if there is a space, it consumes that space and returns the now partially consumed input in input
+    let (input, _) = many0(tag("\n"))(input)?; // many0 applies parser 0 or more times to check for newline "\n" and if there is a newline, it consumes that newline and returns the now partially consumed input in input
+    Ok((input, Node::Statement{ children: vec![result], span}))   
+  }
+
+  // expression_statement = expression , ";" | expression ?last in a block, before its "}"? ;
+  pub fn expression_statement(input: Input) -> IResult<Input, Node, ParseError> {
+    let (input, _) = many0(alt((tag(" "),tag("\t"))))(input)?; // applies parser 0 or more times to recognise space or tab tags 
+    let (input, result) = expression(input)?; // calls expression function which returns the output of the function in result as well as the now partially consumed input in input
+    if let Ok((input, _)) = peek(preceded(multispace0, tag::<_, _, ParseError>("}")))(input) { // peek looks for the "}" closing the block without consuming it, the last expression of a block doesn't need a ";"
+      let span = result.span();
+      return Ok((input, Node::Statement{ children: vec![result], span}));
+    }
+    let (input, semicolon) = tag(";")(input)
+      .map_err(|error: nom::Err<ParseError>| error.map(|_| ParseError::expected(input, "`;`".to_string())))?; // tag recognizes ";" and consumes it and returns partially consumed input in input
+    let span = result.span().to(&Span::between(semicolon, input));
+    Ok((input, Node::Statement{ children: vec![result], span}))
   }
 
-  // statement  = variable_define , ";" ;
-  pub fn statement(input: &str) -> IResult<&str, Node> {
+  // return_statement = function_return , ";" ;
+  pub fn return_statement(input: Input) -> IResult<Input, Node, ParseError> {
     let (input, _) = many0(alt((tag(" "),tag("\t"))))(input)?; // applies parser 0 or more times to recognise space or tab tags 
-    let (input, result) = variable_define(input)?; // calls variable_define function which returns the output of the function in result as well as the now partially consumed input in input
-    let (input, _) = tag(";")(input)?; // tag recognizes ";" and consumes it and returns partially consumed input in input
+    let (input, result) = function_return(input)?; // calls function_return function which returns the output of the function in result as well as the now partially consumed input in input
+    let (input, semicolon) = expect(";")(input)?; // expect recognizes ";" and consumes it and returns partially consumed input in input
+    let span = result.span().to(&Span::between(semicolon, input));
     let (input, _) = many0(tag(" "))(input)?; // many0 applies parser 0 or more times to check for space " " and if there is a space, it consumes that space and returns the now partially consumed input in input
     let (input, _) = many0(tag("\n"))(input)?; // many0 applies parser 0 or more times to check for newline "\n" and if there is a newline, it consumes that newline and returns the now partially consumed input in input
-    Ok((input, Node::Statement{ children: vec![result]}))   
+    Ok((input, Node::Statement{ children: vec![result], span}))   
   }
 
-  // function_return = "return", (function_call | expression | identifier) ;
-  pub fn function_return(input: &str) -> IResult<&str, Node> {
-    let (input, _) = tag("return ")(input)?; // tag recognizes "return " and consumes it and returns partially consumed input in input
-    let (input, return_value) = alt((function_call, expression, identifier))(input)?; // takes function_call, expression, identifier as a list of functions and returns whichever function the parser recognizes in input
-    Ok((input, Node::FunctionReturn{ children: vec![return_value]}))
+  // function_return = "return", expression ;
+  pub fn function_return(input: Input) -> IResult<Input, Node, ParseError> {
+    let (input, keyword) = tag("return ")(input)?; // tag recognizes "return " and consumes it and returns partially consumed input in input
+    let (input, return_value) = cut(context("a value to return", expression))(input)?; // calls expression function which returns the output of the function in return_value as well as the now partially consumed input in input
+    let span = Span::between(keyword, input);
+    Ok((input, Node::FunctionReturn{ children: vec![return_value], span}))
   }
 
   // variable_define = "let" , identifier , "=" , expression ;
-  pub fn variable_define(input: &str) -> IResult<&str, Node> {
-    let (input, _) = tag("let ")(input)?; // tag recognizes "let " and consumes it and returns partially consumed input in input
+  pub fn variable_define(input: Input) -> IResult<Input, Node, ParseError> {
+    let (input, keyword) = tag("let ")(input)?; // tag recognizes "let " and consumes it and returns partially consumed input in input
+    let (input, variable) = cut(context("a variable name", identifier))(input)?; // calls identifier function which returns the output of the function in variable as well as the now partially consumed input in input
+    let (input, _) = many0(tag(" "))(input)?; // many0 applies parser 0 or more times to check for space " " and if there is a space, it consumes that space and returns the now partially consumed input in input
+    let (input, _) = expect("=")(input)?; // expect recognizes "=" and consumes it and returns partially consumed input in input
+    let (input, _) = many0(tag(" "))(input)?; // many0 applies parser 0 or more times to check for space " " and if there is a space, it consumes that space and returns the now partially consumed input in input
+    let (input, expression) = cut(context("an expression", expression))(input)?; // calls expression function which returns the output of the function in expression as well as the now partially consumed input in input
+    let span = Span::between(keyword, input);
+    Ok((input, Node::VariableDefine{ children: vec![variable, expression], span}))   
+  }
+
+  // assignment = identifier , ("=" | "+=" | "-=" | "*=" | "/=" | "%=") , expression ;
+  pub fn assignment(input: Input) -> IResult<Input, Node, ParseError> {
+    let start = input;
     let (input, variable) = identifier(input)?; // calls identifier function which returns the output of the function in variable as well as the now partially consumed input in input
     let (input, _) = many0(tag(" "))(input)?; // many0 applies parser 0 or more times to check for space " " and if there is a space, it consumes that space and returns the now partially consumed input in input
-    let (input, _) = tag("=")(input)?; // tag recognizes "=" and consumes it and returns partially consumed input in input
+    let (input, op) = alt((tag("+="), tag("-="), tag("*="), tag("/="), tag("%="), terminated(tag("="), not(tag("=")))))(input)
+      .map_err(|error: nom::Err<ParseError>| error.map(|_| ParseError::expected(start, "an assignment".to_string())))?; // takes the assignment operators as a list of tags and returns whichever one the parser recognizes in op, a plain "=" can't be the start of "==". Without one it was never an assignment, so the error points back at the start
     let (input, _) = many0(tag(" "))(input)?; // many0 applies parser 0 or more times to check for space " " and if there is a space, it consumes that space and returns the now partially consumed input in input
-    let (input, expression) = expression(input)?; // calls expression function which returns the output of the function in expression as well as the now partially consumed input in input
-    Ok((input, Node::VariableDefine{ children: vec![variable, expression]}))   
+    let (input, expression) = cut(context("an expression", expression))(input)?; // calls expression function which returns the output of the function in expression as well as the now partially consumed input in input
+    let span = variable.span().to(&expression.span());
+    Ok((input, Node::Assignment{ name: op.to_string(), children: vec![variable, expression], span}))
   }
 
-//   if = "if", conditional_ex, "{", [statement], function_return, "}" ; 
-pub fn if_statement(input: &str) -> IResult<&str, Node> {
-    let (input, _) = tag("if ")(input)?; // tag recognizes "if " and consumes it and returns partially consumed input in input
-    let (input, _) = many0(tag(" "))(input)?;  // many0 applies parser 0 or more times to check for space " " and if there is a space, it consumes that space and returns the now partially consumed input in input
-    let (input, conditional_ex) = conditional_exp(input)?; // calls conditional_exp function which returns the output of the function in conditional_ex as well as the now partially consumed input in input
+// if = "if", logical_exp, block ;
+pub fn if_statement(input: Input) -> IResult<Input, Node, ParseError> {
+    let (input, keyword) = tag("if ")(input)?; // tag recognizes "if " and consumes it and returns partially consumed input in input, from here on it can only be an if
     let (input, _) = many0(tag(" "))(input)?;  // many0 applies parser 0 or more times to check for space " " and if there is a space, it consumes that space and returns the now partially consumed input in input
+    let (input, conditional_ex) = cut(context("a condition", logical_exp))(input)?; // calls logical_exp function which returns the output of the function in conditional_ex as well as the now partially consumed input in input
     let (input, _) = many0(tag(" "))(input)?;  // many0 applies parser 0 or more times to check for space " " and if there is a space, it consumes that space and returns the now partially consumed input in input
-    let (input, _) = tag("{")(input)?; // tag recognizes "{" and consumes it and returns partially consumed input in input
-    let (input, _) = many0(tag("\n"))(input)?; // many0 applies parser 0 or more times to check for newline "\n" and if there is a newline, it consumes that newline and returns the now partially consumed input in input
-    let (input, _) = many0(tag(" "))(input)?; // many0 applies parser 0 or more times to check for space " " and if there is a space, it consumes that space and returns the now partially consumed input in input
-    let (input, _) = many0(tag("\t"))(input)?; // many0 applies parser 0 or more times to check for tab "\t" and if there is a tab, it consumes that tab and returns the now partially consumed input in input
-    let (input, _) = many0(tag(" "))(input)?;  // many0 applies parser 0 or more times to check for space " " and if there is a space, it consumes that space and returns the now partially consumed input in input
-    let (input, mut statements) = many0(statement)(input)?; // many0 applies parser 0 or more times to call statement function which returns the output of the function in statements as well as the now partially consumed input in input
-    let (input, _) = many0(tag("\n"))(input)?; // many0 applies parser 0 or more times to check for newline "\n" and if there is a newline, it consumes that newline and returns the now partially consumed input in input
-    let (input, _) = many0(tag(" "))(input)?; // many0 applies parser 0 or more times to check for space " " and if there is a space, it consumes that space and returns the now partially consumed input in input
-    let (input, _) = many0(tag("\t"))(input)?; // many0 applies parser 0 or more times to check for tab "\t" and if there is a tab, it consumes that tab and returns the now partially consumed input in input
-    let (input, mut return_stat) = many1(function_return)(input)?;  // many1 applies parser 1 or more times to call function_return function which returns the output of the function in return_stat as well as the now partially consumed input in input
-    let (input, _) = tag(";")(input)?; // tag recognizes "; " and consumes it and returns partially consumed input in input
-    let (input, _) = many0(tag("\n"))(input)?; // many0 applies parser 0 or more times to check for newline "\n" and if there is a newline, it consumes that newline and returns the now partially consumed input in input
-    let (input, _) = many0(tag(" "))(input)?; // many0 applies parser 0 or more times to check for space " " and if there is a space, it consumes that space and returns the now partially consumed input in input
-    let (input, _) = many0(tag("\t"))(input)?; // many0 applies parser 0 or more times to check for tab "\t" and if there is a tab, it consumes that tab and returns the now partially consumed input in input
-    let (input, _) = tag("}")(input)?; // tag recognizes "}" and consumes it and returns partially consumed input in input
-    let (input, _) = many0(tag("\n"))(input)?; // many0 applies parser 0 or more times to check for newline "\n" and if there is a newline, it consumes that newline and returns the now partially consumed input in input
-    let (input, _) = many0(tag(" "))(input)?; // many0 applies parser 0 or more times to check for space " " and if there is a space, it consumes that space and returns the now partially consumed input in input
-    let (input, _) = many0(tag("\t"))(input)?; // many0 applies parser 0 or more times to check for tab "\t" and if there is a tab, it consumes that tab and returns the now partially consumed input in input
-    let mut children_temp = vec![conditional_ex.clone()]; 
+    let (input, mut statements) = block("the `if` block".to_string())(input)?; // calls block function which returns the statements of the block in statements as well as the now partially consumed input in input
+    let span = Span::between(keyword, input);
+    let (input, _) = multispace0(input)?; // multispace0 consumes any spaces, tabs and newlines and returns the now partially consumed input in input
+    let mut children_temp = vec![conditional_ex];
     children_temp.append(&mut statements); // adds statements to children_temp vector 
-    children_temp.append(&mut return_stat); // adds return_stat to children_temp vector 
-   Ok((input, Node::IfStatement{ children: children_temp}))  
+    Ok((input, Node::IfStatement{ children: children_temp, span}))
 }
 
-// else =  "else", "{", [statement], function_return, "}" ;
-pub fn else_statement(input: &str) -> IResult<&str, Node> {
-    let (input, _) = tag("else ")(input)?; // tag recognizes "else " and consumes it and returns partially consumed input in input
-    let (input, _) = tag("{")(input)?; // tag recognizes "{" and consumes it and returns partially consumed input in input
-    let (input, _) = many0(tag("\n"))(input)?; // many0 applies parser 0 or more times to check for newline "\n" and if there is a newline, it consumes that newline and returns the now partially consumed input in input
-    let (input, _) = many0(tag(" "))(input)?; // many0 applies parser 0 or more times to check for space " " and if there is a space, it consumes that space and returns the now partially consumed input in input
-    let (input, _) = many0(tag("\t"))(input)?;  // many0 applies parser 0 or more times to check for tab "\t" and if there is a tab, it consumes that tab and returns the now partially consumed input in input
-    let (input, mut statements) = many0(statement)(input)?; // many0 applies parser 0 or more times to call statement function which returns the output of the function in statements as well as the now partially consumed input in input
-    let (input, _) = many0(tag("\n"))(input)?; // many0 applies parser 0 or more times to check for newline "\n" and if there is a newline, it consumes that newline and returns the now partially consumed input in input
-    let (input, _) = many0(tag(" "))(input)?; // many0 applies parser 0 or more times to check for space " " and if there is a space, it consumes that space and returns the now partially consumed input in input
-    let (input, _) = many0(tag("\t"))(input)?; // many0 applies parser 0 or more times to check for tab "\t" and if there is a tab, it consumes that tab and returns the now partially consumed input in input
-    let (input, mut return_stat) = many1(function_return)(input)?; // many1 applies parser 1 or more times to call function_return function which returns the output of the function in return_stat as well as the now partially consumed input in input
-    let (input, _) = tag(";")(input)?; // tag recognizes "; " and consumes it and returns partially consumed input in input
-    let (input, _) = many0(tag("\n"))(input)?; // many0 applies parser 0 or more times to check for newline "\n" and if there is a newline, it consumes that newline and returns the now partially consumed input in input
+// else = "else", block ;
+pub fn else_statement(input: Input) -> IResult<Input, Node, ParseError> {
+    let (input, keyword) = tag("else ")(input)?; // tag recognizes "else " and consumes it and returns partially consumed input in input
+    let (input, _) = many0(tag(" "))(input)?;  // many0 applies parser 0 or more times to check for space " " and if there is a space, it consumes that space and returns the now partially consumed input in input
+    let (input, statements) = block("the `else` block".to_string())(input)?; // calls block function which returns the statements of the block in statements as well as the now partially consumed input in input
+    let span = Span::between(keyword, input);
+    let (input, _) = multispace0(input)?; // multispace0 consumes any spaces, tabs and newlines and returns the now partially consumed input in input
+    Ok((input, Node::ElseStatement{ children: statements, span}))
+}
+
+// else_if = "else if", logical_exp, block ;
+pub fn else_if(input: Input) -> IResult<Input, Node, ParseError> {
+    let (input, keyword) = tag("else if ")(input)?; // tag recognizes "else if " and consumes it and returns partially consumed input in input
     let (input, _) = many0(tag(" "))(input)?; // many0 applies parser 0 or more times to check for space " " and if there is a space, it consumes that space and returns the now partially consumed input in input
-    let (input, _) = many0(tag("\t"))(input)?; // many0 applies parser 0 or more times to check for tab "\t" and if there is a tab, it consumes that tab and returns the now partially consumed input in input
-    let (input, _) = tag("}")(input)?; // tag recognizes "} " and consumes it and returns partially consumed input in input
-    let (input, _) = many0(tag("\n"))(input)?; // many0 applies parser 0 or more times to check for newline "\n" and if there is a newline, it consumes that newline and returns the now partially consumed input in input
+    let (input, conditional_ex) = cut(context("a condition", logical_exp))(input)?; // calls logical_exp function which returns the output of the function in conditional_ex as well as the now partially consumed input in input
     let (input, _) = many0(tag(" "))(input)?; // many0 applies parser 0 or more times to check for space " " and if there is a space, it consumes that space and returns the now partially consumed input in input
-    let (input, _) = many0(tag("\t"))(input)?; // many0 applies parser 0 or more times to check for tab "\t" and if there is a tab, it consumes that tab and returns the now partially consumed input in input
-    let mut children_temp = vec![];
+    let (input, mut statements) = block("the `else if` block".to_string())(input)?; // calls block function which returns the statements of the block in statements as well as the now partially consumed input in input
+    let span = Span::between(keyword, input);
+    let (input, _) = multispace0(input)?; // multispace0 consumes any spaces, tabs and newlines and returns the now partially consumed input in input
+    let mut children_temp = vec![conditional_ex];
     children_temp.append(&mut statements); // adds statements to children_temp vector 
-    children_temp.append(&mut return_stat); // adds return_stat to children_temp vector 
-    return Ok((input, Node::ElseStatement{ children: children_temp}))
+    Ok((input, Node::ElseIfStatement{ children: children_temp, span}))
+}
+
+// if_else = if, [{else_if}], [else] ;
+pub fn if_else_statements(input: Input) -> IResult<Input, Node, ParseError> {
+    let (input, if_stat) = if_statement(input)?; // calls if_statement function which returns the output of the function in if_stat as well as the now partially consumed input in input
+    let (input, mut else_if_stat) = many0(else_if)(input)?; // many0 applies parser 0 or more times to call else_if function which returns the output of the function in else_if_stat as well as the now partially consumed input in input
+    let (input, else_stat) = opt(else_statement)(input)?; // opt applies else_statement at most once, the else block is optional and without it else_stat is None. The now partially consumed input is returned in input
+    let mut children_temp = vec![if_stat];
+    children_temp.append(&mut else_if_stat); // adds else_if_stat to children_temp vector 
+    children_temp.extend(else_stat); // adds the else block to children_temp vector if there is one
+    let span = children_temp[0].span().to(&children_temp[children_temp.len() - 1].span()); // runs from the if to the end of the last block
+    Ok((input, Node::IfElseStatements{ children: children_temp, span}))
+}
+
+// if_expression = if, [{else_if}], else ;
+// An if used as a value. Without an `else` there would be nothing to evaluate to when no condition holds, so here it's
+// required.
+pub fn if_expression(input: Input) -> IResult<Input, Node, ParseError> {
+    let (input, if_stat) = if_statement(input)?; // calls if_statement function which returns the output of the function in if_stat as well as the now partially consumed input in input
+    let (input, mut else_if_stat) = many0(else_if)(input)?; // many0 applies parser 0 or more times to call else_if function which returns the output of the function in else_if_stat as well as the now partially consumed input in input
+    let (input, else_stat) = cut(context("an `else` block", else_statement))(input)?; // calls else_statement function which returns the output of the function in else_stat as well as the now partially consumed input in input, once there's an if it has to end in an else
+    let span = if_stat.span().to(&else_stat.span()); // runs from the if to the end of the else block
+    let mut children_temp = vec![if_stat];
+    children_temp.append(&mut else_if_stat); // adds else_if_stat to children_temp vector 
+    children_temp.push(else_stat); // adds else_stat to children_temp vector 
+    Ok((input, Node::IfElseStatements{ children: children_temp, span}))
+}
+
+// block_item = statement | return_statement | if_else | while | for | break | continue | expression_statement ;
+pub fn block_item(input: Input) -> IResult<Input, Node, ParseError> {
+    alt((statement, return_statement, if_else_statements, while_statement, for_statement, break_statement, continue_statement, expression_statement))(input) // takes everything that can appear in a block as a list of functions and returns whichever function the parser recognizes in input
 }
 
-// else_if = "else if", conditional_ex, "{", [statement], function_return, "}" ;
-pub fn else_if(input: &str) -> IResult<&str, Node> {
-    let (input, _) = tag("else if ")(input)?; // tag recognizes "else if " and consumes it and returns partially consumed input in input
+// block = "{", {block_item}, "}" ;
+// Function bodies, loop bodies and the blocks of an if all share this, `what` names the block in the error when its
+// closing "}" is missing.
+pub fn block<'a>(what: String) -> impl FnMut(Input<'a>) -> IResult<Input<'a>, Vec<Node>, ParseError> {
+    move |input| {
+        let (input, open) = expect("{")(input)?; // expect recognizes "{" and consumes it and returns partially consumed input in input
+        let (input, _) = multispace0(input)?; // multispace0 consumes any spaces, tabs and newlines and returns the now partially consumed input in input
+        let (input, statements) = many0(terminated(block_item, multispace0))(input)?; // many0 applies parser 0 or more times to call block_item function, skipping the whitespace after each one, and returns all of them in statements
+        // when the statements stop before the "}", the one they stopped on is parsed again on its own, if it got
+        // anywhere before failing its error says what went wrong better than a missing "}" would
+        if let Err(nom::Err::Error(error)) = block_item(input) {
+            if error.span.start > input.location_offset() {
+                return Err(nom::Err::Failure(error));
+            }
+        }
+        let (input, _) = closing("}", open, what.clone())(input)?; // closing recognizes "}" and consumes it and returns partially consumed input in input
+        Ok((input, statements))
+    }
+}
+
+// while = "while", logical_exp, block ;
+pub fn while_statement(input: Input) -> IResult<Input, Node, ParseError> {
+    let (input, _) = many0(alt((tag(" "),tag("\t"))))(input)?; // applies parser 0 or more times to recognise space or tab tags 
+    let (input, keyword) = tag("while ")(input)?; // tag recognizes "while " and consumes it and returns partially consumed input in input, from here on it can only be a loop
     let (input, _) = many0(tag(" "))(input)?; // many0 applies parser 0 or more times to check for space " " and if there is a space, it consumes that space and returns the now partially consumed input in input
-    let (input, conditional_ex) = conditional_exp(input)?; // calls conditional_exp function which returns the output of the function in conditional_ex as well as the now partially consumed input in input
+    let (input, condition) = cut(context("a condition", logical_exp))(input)?; // calls logical_exp function which returns the output of the function in condition as well as the now partially consumed input in input
     let (input, _) = many0(tag(" "))(input)?; // many0 applies parser 0 or more times to check for space " " and if there is a space, it consumes that space and returns the now partially consumed input in input
+    let (input, mut body) = block("the `while` loop".to_string())(input)?; // calls block function which returns the statements of the body in body as well as the now partially consumed input in input
+    let span = Span::between(keyword, input);
+    let (input, _) = multispace0(input)?; // multispace0 consumes any spaces, tabs and newlines and returns the now partially consumed input in input
+    let mut children = vec![condition];
+    children.append(&mut body); // adds body to children vector 
+    Ok((input, Node::WhileStatement{ children, span}))
+}
+
+// for = "for", identifier, "in", range, block ;
+pub fn for_statement(input: Input) -> IResult<Input, Node, ParseError> {
+    let (input, _) = many0(alt((tag(" "),tag("\t"))))(input)?; // applies parser 0 or more times to recognise space or tab tags 
+    let (input, keyword) = tag("for ")(input)?; // tag recognizes "for " and consumes it and returns partially consumed input in input, from here on it can only be a loop
     let (input, _) = many0(tag(" "))(input)?; // many0 applies parser 0 or more times to check for space " " and if there is a space, it consumes that space and returns the now partially consumed input in input
-    let (input, _) = tag("{")(input)?; // tag recognizes "{" and consumes it and returns partially consumed input in input
-    let (input, _) = many0(tag("\n"))(input)?; // many0 applies parser 0 or more times to check for newline "\n" and if there is a newline, it consumes that newline and returns the now partially consumed input in input
+    let (input, variable) = cut(context("a loop variable", identifier))(input)?; // calls identifier function which returns the output of the function in variable as well as the now partially consumed input in input
     let (input, _) = many0(tag(" "))(input)?; // many0 applies parser 0 or more times to check for space " " and if there is a space, it consumes that space and returns the now partially consumed input in input
-    let (input, _) = many0(tag("\t"))(input)?; // many0 applies parser 0 or more times to check for tab "\t" and if there is a tab, it consumes that tab and returns the now partially consumed input in input
+    let (input, _) = expect("in")(input)?; // expect recognizes "in" and consumes it and returns partially consumed input in input
     let (input, _) = many0(tag(" "))(input)?; // many0 applies parser 0 or more times to check for space " " and if there is a space, it consumes that space and returns the now partially consumed input in input
-    let (input, mut statements) = many0(statement)(input)?; // many0 applies parser 0 or more times to call statement function which returns the output of the function in statements as well as the now partially consumed input in input
-    let (input, _) = many0(tag("\n"))(input)?; // many0 applies parser 0 or more times to check for newline "\n" and if there is a newline, it consumes that newline and returns the now partially consumed input in input
+    let (input, range) = cut(context("a range", range))(input)?; // calls range function which returns the output of the function in range as well as the now partially consumed input in input
     let (input, _) = many0(tag(" "))(input)?; // many0 applies parser 0 or more times to check for space " " and if there is a space, it consumes that space and returns the now partially consumed input in input
-    let (input, _) = many0(tag("\t"))(input)?; // many0 applies parser 0 or more times to check for tab "\t" and if there is a tab, it consumes that tab and returns the now partially consumed input in input
-    let (input, mut return_stat) = many1(function_return)(input)?; // many1 applies parser 1 or more times to call function_return function which returns the output of the function in return_stat as well as the now partially consumed input in input
-    let (input, _) = tag(";")(input)?; // tag recognizes "; " and consumes it and returns partially consumed input in input
-    let (input, _) = many0(tag("\n"))(input)?; // many0 applies parser 0 or more times to check for newline "\n" and if there is a newline, it consumes that newline and returns the now partially consumed input in input
+    let (input, mut body) = block("the `for` loop".to_string())(input)?; // calls block function which returns the statements of the body in body as well as the now partially consumed input in input
+    let span = Span::between(keyword, input);
+    let (input, _) = multispace0(input)?; // multispace0 consumes any spaces, tabs and newlines and returns the now partially consumed input in input
+    let mut children = vec![variable, range];
+    children.append(&mut body); // adds body to children vector 
+    Ok((input, Node::ForStatement{ children, span}))
+}
+
+// range = math_expression, (".." | "..="), math_expression ;
+pub fn range(input: Input) -> IResult<Input, Node, ParseError> {
+    let (input, start) = math_expression(input)?; // calls math_expression function which returns the output of the function in start as well as the now partially consumed input in input
     let (input, _) = many0(tag(" "))(input)?; // many0 applies parser 0 or more times to check for space " " and if there is a space, it consumes that space and returns the now partially consumed input in input
-    let (input, _) = many0(tag("\t"))(input)?; // many0 applies parser 0 or more times to check for tab "\t" and if there is a tab, it consumes that tab and returns the now partially consumed input in input
-    let (input, _) = tag("}")(input)?; // tag recognizes "}" and consumes it and returns partially consumed input in input
-    let (input, _) = many0(tag("\n"))(input)?; // many0 applies parser 0 or more times to check for newline "\n" and if there is a newline, it consumes that newline and returns the now partially consumed input in input
+    let (input, op) = cut(context("`..` or `..=`", alt((tag("..="), tag("..")))))(input)?; // takes ..= and .. as a list of tags and returns whichever tag the parser recognizes in op, the longer one has to be tried first
     let (input, _) = many0(tag(" "))(input)?; // many0 applies parser 0 or more times to check for space " " and if there is a space, it consumes that space and returns the now partially consumed input in input
-    let (input, _) = many0(tag("\t"))(input)?; // many0 applies parser 0 or more times to check for tab "\t" and if there is a tab, it consumes that tab and returns the now partially consumed input in input
-    let mut children_temp = vec![conditional_ex.clone()];
-    children_temp.append(&mut statements); // adds statements to children_temp vector 
-    children_temp.append(&mut return_stat); // adds return_stat to children_temp vector 
-    return Ok((input, Node::ElseIfStatement{ children: children_temp}))
+    let (input, end) = cut(context("the end of the range", math_expression))(input)?; // calls math_expression function which returns the output of the function in end as well as the now partially consumed input in input
+    let span = start.span().to(&end.span());
+    Ok((input, Node::Range{ name: op.to_string(), children: vec![start, end], span}))
 }
 
-// if_else = if, [else_if], else ;
-pub fn if_else_statements(input: &str) -> IResult<&str, Node> {
-    let (input, if_stat) = if_statement(input)?; // calls if_statement function which returns the output of the function in if_stat as well as the now partially consumed input in input
-    let (input, mut else_if_stat) = many0(else_if)(input)?; // many0 applies parser 0 or more times to call else_if function which returns the output of the function in else_if_stat as well as the now partially consumed input in input
-    let (input, mut else_stat) = many1(else_statement)(input)?; // many1 applies parser 1 or more times to call else_statement function which returns the output of the function in else_stat as well as the now partially consumed input in input
-    let mut children_temp = vec![if_stat.clone()];
-    children_temp.append(&mut else_if_stat); // adds else_if_stat to children_temp vector 
-    children_temp.append(&mut else_stat); // adds else_stat to children_temp vector 
-    return Ok((input, Node::IfElseStatements{ children: children_temp}))
+// break = "break", ";" ;
+pub fn break_statement(input: Input) -> IResult<Input, Node, ParseError> {
+    let (input, _) = many0(alt((tag(" "),tag("\t"))))(input)?; // applies parser 0 or more times to recognise space or tab tags 
+    let (input, keyword) = terminated(tag("break"), not(alphanumeric1))(input)?; // tag recognizes "break" and consumes it and returns partially consumed input in input, as long as it isn't the start of a longer name
+    let (input, _) = expect(";")(input)?; // expect recognizes ";" and consumes it and returns partially consumed input in input
+    let span = Span::between(keyword, input);
+    let (input, _) = multispace0(input)?; // multispace0 consumes any spaces, tabs and newlines and returns the now partially consumed input in input
+    Ok((input, Node::BreakStatement{ span }))
+}
+
+// continue = "continue", ";" ;
+pub fn continue_statement(input: Input) -> IResult<Input, Node, ParseError> {
+    let (input, _) = many0(alt((tag(" "),tag("\t"))))(input)?; // applies parser 0 or more times to recognise space or tab tags 
+    let (input, keyword) = terminated(tag("continue"), not(alphanumeric1))(input)?; // tag recognizes "continue" and consumes it and returns partially consumed input in input, as long as it isn't the start of a longer name
+    let (input, _) = expect(";")(input)?; // expect recognizes ";" and consumes it and returns partially consumed input in input
+    let span = Span::between(keyword, input);
+    let (input, _) = multispace0(input)?; // multispace0 consumes any spaces, tabs and newlines and returns the now partially consumed input in input
+    Ok((input, Node::ContinueStatement{ span }))
 }
 
+// logical_exp = logical_and, [{logical_or_infix}] ;
+// Conditions combine with `||`, `&&` and `!`, which all bind more loosely than the comparisons: `!` is applied to a
+// whole comparison and `&&` binds tighter than `||`.
+pub fn logical_exp(input: Input) -> IResult<Input, Node, ParseError> {
+    let (input, mut head) = logical_and(input)?; // calls logical_and function which returns the output of the function in head as well as the now partially consumed input in input
+    let (input, tail) = many0(logical_or_infix)(input)?; // applies parser 0 or more times to recognise logical_or_infix function and returns in tail 
+    for n in tail { // loops through each element n in tail and matches to logical expression node
+      if let Node::LogicalExpression{name, mut children, span} = n {
+        let span = head.span().to(&span); // the expression runs from the start of head to the end of this operand
+        let mut new_children = vec![head.clone()];
+        new_children.append(&mut children);
+        head = Node::LogicalExpression{name, children: new_children, span};
+      }
+    }
+    Ok((input, head))
+}
 
-// conditional_exp = conditional_val, conditional_operator, conditional_val, [conditional_operator, conditional_val] ;
-pub fn conditional_exp(input: &str) -> IResult<&str, Node> {
-    let (input, conditional_val1) = conditional_val(input)?; // calls conditional_val function which returns the output of the function in conditional_val1 as well as the now partially consumed input in input
+// logical_or_infix = "||", logical_and ;
+pub fn logical_or_infix(input: Input) -> IResult<Input, Node, ParseError> {
     let (input, _) = many0(tag(" "))(input)?; // many0 applies parser 0 or more times to check for space " " and if there is a space, it consumes that space and returns the now partially consumed input in input
-    let (input, conditional_op1) = conditional_operator(input)?; // calls conditional_operator function which returns the output of the function in conditional_op1 as well as the now partially consumed input in input
+    let (input, op) = tag("||")(input)?; // tag recognizes "||" and consumes it and returns partially consumed input in input
     let (input, _) = many0(tag(" "))(input)?; // many0 applies parser 0 or more times to check for space " " and if there is a space, it consumes that space and returns the now partially consumed input in input
-    let (input, conditional_val2) = conditional_val(input)?; // calls conditional_val function which returns the output of the function in conditional_val2 as well as the now partially consumed input in input
+    let (input, args) = cut(context("a condition", logical_and))(input)?; // calls logical_and function which returns the output of the function in args as well as the now partially consumed input in input
+    let span = Span::between(op, input);
+    Ok((input, Node::LogicalExpression{name: op.to_string(), children: vec![args], span}))
+}
+
+// logical_and = logical_not, [{logical_and_infix}] ;
+pub fn logical_and(input: Input) -> IResult<Input, Node, ParseError> {
+    let (input, mut head) = logical_not(input)?; // calls logical_not function which returns the output of the function in head as well as the now partially consumed input in input
+    let (input, tail) = many0(logical_and_infix)(input)?; // applies parser 0 or more times to recognise logical_and_infix function and returns in tail 
+    for n in tail { // loops through each element n in tail and matches to logical expression node
+      if let Node::LogicalExpression{name, mut children, span} = n {
+        let span = head.span().to(&span); // the expression runs from the start of head to the end of this operand
+        let mut new_children = vec![head.clone()];
+        new_children.append(&mut children);
+        head = Node::LogicalExpression{name, children: new_children, span};
+      }
+    }
+    Ok((input, head))
+}
+
+// logical_and_infix = "&&", logical_not ;
+pub fn logical_and_infix(input: Input) -> IResult<Input, Node, ParseError> {
     let (input, _) = many0(tag(" "))(input)?; // many0 applies parser 0 or more times to check for space " " and if there is a space, it consumes that space and returns the now partially consumed input in input
-    let (input, mut conditional_op2) = many0(conditional_operator)(input)?; // many0 applies parser 0 or more times to call conditional_operator function which returns the output of the function in conditional_op2 as well as the now partially consumed input in input
+    let (input, op) = tag("&&")(input)?; // tag recognizes "&&" and consumes it and returns partially consumed input in input
     let (input, _) = many0(tag(" "))(input)?; // many0 applies parser 0 or more times to check for space " " and if there is a space, it consumes that space and returns the now partially consumed input in input
-    let (input, mut conditional_val3) = many0(conditional_val)(input)?; // many0 applies parser 0 or more times to call conditional_val function which returns the output of the function in conditional_val3 as well as the now partially consumed input in input
-    let mut children = vec![conditional_val1, conditional_op1, conditional_val2];
-    children.append(&mut conditional_op2); // adds conditional_op2 to children_temp vector 
-    children.append(&mut conditional_val3); // adds conditional_val3 to children_temp vector 
-    Ok((input, Node::ConditionalExpression{ children: children})) 
+    let (input, args) = cut(context("a condition", logical_not))(input)?; // calls logical_not function which returns the output of the function in args as well as the now partially consumed input in input
+    let span = Span::between(op, input);
+    Ok((input, Node::LogicalExpression{name: op.to_string(), children: vec![args], span}))
 }
 
-// conditional_operator      = "<" | ">" | "<=" | ">=" | "==" | "!=" ;
-pub fn conditional_operator(input: &str) -> IResult<&str, Node> {
-    let (input, conditional_op) = alt((tag("<"), tag(">"), tag("<="), tag(">="), tag("=="), tag("!=")))(input)?; // takes <, >, <=, >=, ==, != as a list of tags and returns whichever tag the parser recognizes in input 
-    Ok((input, Node::ConditionalOperator{ value: conditional_op.to_string()})) // Return the now partially consumed input, as well as a node with the string on it
+// logical_not = ("!", logical_not) | conditional_exp ;
+pub fn logical_not(input: Input) -> IResult<Input, Node, ParseError> {
+    if let Ok((input, op)) = tag::<_, _, ParseError>("!")(input) { // tag recognizes "!" and consumes it and returns partially consumed input in input
+      let (input, _) = many0(tag(" "))(input)?; // many0 applies parser 0 or more times to check for space " " and if there is a space, it consumes that space and returns the now partially consumed input in input
+      let (input, operand) = cut(context("a condition", logical_not))(input)?; // calls logical_not function which returns the output of the function in operand as well as the now partially consumed input in input
+      let span = Span::between(op, input);
+      return Ok((input, Node::UnaryExpression{name: op.to_string(), children: vec![operand], span}));
+    }
+    conditional_exp(input) // calls conditional_exp function
+}
+
+// conditional_exp = math_expression, [{conditional_infix}] ;
+// Comparisons chain to the left: `a < b == c` compares `a < b` first and then compares that bool to `c`, the same as
+// `(a < b) == c`.
+pub fn conditional_exp(input: Input) -> IResult<Input, Node, ParseError> {
+    let (input, mut head) = math_expression(input)?; // calls math_expression function which returns the output of the function in head as well as the now partially consumed input in input
+    let (input, tail) = many0(conditional_infix)(input)?; // applies parser 0 or more times to recognise conditional_infix function and returns in tail 
+    for n in tail { // loops through each element n in tail and matches to conditional expression node
+      if let Node::ConditionalExpression{name, mut children, span} = n {
+        let span = head.span().to(&span); // the expression runs from the start of head to the end of this operand
+        let mut new_children = vec![head.clone()];
+        new_children.append(&mut children);
+        head = Node::ConditionalExpression{name, children: new_children, span};
+      }
+    }
+    Ok((input, head))
 }
 
-// conditional_val = number | boolean | identifier | math_expression ;
-pub fn conditional_val(input: &str) -> IResult<&str, Node> {
-    let (input, result) = alt((boolean, math_expression, number, identifier))(input)?; // takes boolean, math_expression, number, identifier as a list of functions and returns whichever function the parser recognizes in input 
-    Ok((input, Node::ConditionalValue{ children: vec![result]}))   
+// conditional_infix = ("<=" | ">=" | "==" | "!=" | "<" | ">"), math_expression ;
+pub fn conditional_infix(input: Input) -> IResult<Input, Node, ParseError> {
+    let (input, _) = many0(tag(" "))(input)?; // many0 applies parser 0 or more times to check for space " " and if there is a space, it consumes that space and returns the now partially consumed input in input
+    let (input, op) = alt((tag("<="), tag(">="), tag("=="), tag("!="), tag("<"), tag(">")))(input)?; // takes <=, >=, ==, !=, <, > as a list of tags and returns whichever tag the parser recognizes in input, the two character operators go first so "<=" isn't read as "<"
+    let (input, _) = many0(tag(" "))(input)?; // many0 applies parser 0 or more times to check for space " " and if there is a space, it consumes that space and returns the now partially consumed input in input
+    let (input, args) = cut(context("a value to compare", math_expression))(input)?; // calls math_expression function which returns the output of the function in args as well as the now partially consumed input in input
+    let span = Span::between(op, input);
+    Ok((input, Node::ConditionalExpression{name: op.to_string(), children: vec![args], span}))
 }
 
 // arguments  = expression , [other_arg] ;
-  pub fn arguments(input: &str) -> IResult<&str, Node> {
+  pub fn arguments(input: Input) -> IResult<Input, Node, ParseError> {
     let (input, arg) = expression(input)?; // calls expression function which returns the output of the function in arg as well as the now partially consumed input in input
     let (input, mut others) = many0(other_arg)(input)?; // many0 applies parser 0 or more times to call other_arg function which returns the output of the function in others as well as the now partially consumed input in input
+    let span = arg.span().to(&others.last().unwrap_or(&arg).span()); // runs from the first argument to the last one
     let mut args = vec![arg];
     args.append(&mut others); // adds others to args vector 
-    Ok((input, Node::FunctionArguments{children: args}))
+    Ok((input, Node::FunctionArguments{children: args, span}))
   }
 
   // other_arg = ",", expression ; 
-  pub fn other_arg(input: &str) -> IResult<&str, Node> {
+  pub fn other_arg(input: Input) -> IResult<Input, Node, ParseError> {
     let (input, _) = tag(",")(input)?; // tag recognizes "," and consumes it and returns partially consumed input in input
     expression(input)
   }
 
-  // function_definition  = "fn" , identifier , "(" , [arguments] , ")" , "{" , {statement} , "}" 
-  pub fn function_definition(input: &str) -> IResult<&str, Node> {
-    let (input, _) = tag("fn ")(input)?; // tag recognizes "fn" and consumes it and returns partially consumed input in input
-    let (input, function_name) = identifier(input)?;  // calls identifier function which returns the output of the function in function_name as well as the now partially consumed input in input
-    let (input, _) = tag("(")(input)?; // tag recognizes "(" and consumes it and returns partially consumed input in input
+  // function_definition  = "fn" , identifier , "(" , [arguments] , ")" , block 
+  pub fn function_definition(input: Input) -> IResult<Input, Node, ParseError> {
+    let (input, keyword) = tag("fn ")(input)?; // tag recognizes "fn" and consumes it and returns partially consumed input in input
+    let (input, function_name) = cut(context("a function name", identifier))(input)?;  // calls identifier function which returns the output of the function in function_name as well as the now partially consumed input in input
+    let (input, open_paren) = expect("(")(input)?; // expect recognizes "(" and consumes it and returns partially consumed input in input
     let (input, mut args) = many0(arguments)(input)?; // many0 applies parser 0 or more times to call arguments function which returns the output of the function in args as well as the now partially consumed input in input
-    let (input, _) = tag(")")(input)?; // tag recognizes ")" and consumes it and returns partially consumed input in input
+    let (input, _) = closing(")", open_paren, "the parameter list".to_string())(input)?; // closing recognizes ")" and consumes it and returns partially consumed input in input
     let (input, _) = many0(tag(" "))(input)?; // many0 applies parser 0 or more times to check for space " " and if there is a space, it consumes that space and returns the now partially consumed input in input
-    let (input, _) = tag("{")(input)?; // tag recognizes "{" and consumes it and returns partially consumed input in input
-    let (input, _) = many0(tag("\n"))(input)?; // many0 applies parser 0 or more times to check for newline "\n" and if there is a newline, it consumes that newline and returns the now partially consumed input in input
-    let (input, mut statements) = many1(statement)(input)?; // many1 applies parser 1 or more times to call statement function which returns the output of the function in statements as well as the now partially consumed input in input
-    let (input, _) = tag("}")(input)?; // tag recognizes "}" and consumes it and returns partially consumed input in input
+    let name = match &function_name { Node::Identifier{ value, .. } => value.clone(), _ => String::new() };
+    let (input, mut statements) = block(format!("the body of `{}`", name))(input)?; // calls block function which returns the statements of the body in statements as well as the now partially consumed input in input
+    let span = Span::between(keyword, input);
     let (input, _) = many0(alt((tag("\n"),tag(" "))))(input)?; // many0 applies parser 0 or more times to check for newline "\n" or " "and if either are there it consumes that and returns the now partially consumed input in input
     let mut children = vec![function_name];
-    println!("args, {:?}", args);
     children.append(&mut args); // appends args in children vector 
     children.append(&mut statements);  // appends statements in children vector 
-    Ok((input, Node::FunctionDefine{ children: children }))   
+    Ok((input, Node::FunctionDefine{ children, span }))   
+  }
+
+  // top_level_statement = (variable_define | assignment | expression) , (";" | ?the end of the line or of the input?) ;
+  // At the top level the ";" can be left off at the end of a line. Anywhere else a missing one is an error, rather
+  // than the start of another item.
+  pub fn top_level_statement(input: Input) -> IResult<Input, Node, ParseError> {
+    let (input, _) = many0(alt((tag(" "),tag("\t"))))(input)?; // applies parser 0 or more times to recognise space or tab tags 
+    let (input, result) = alt((variable_define, assignment, expression))(input)?; // calls variable_define, assignment or expression function which returns the output of the function in result as well as the now partially consumed input in input
+    let (rest, _) = many0(alt((tag(" "),tag("\t"))))(input)?; // applies parser 0 or more times to recognise space or tab tags 
+    if let Ok((rest, semicolon)) = tag::<_, _, ParseError>(";")(rest) { // tag recognizes ";" and consumes it and returns partially consumed input in rest
+      let span = result.span().to(&Span::between(semicolon, rest));
+      return Ok((rest, Node::Statement{ children: vec![result], span}));
+    }
+    if rest.fragment().is_empty() || rest.fragment().starts_with('\n') || rest.fragment().starts_with("\r\n") {
+      return Ok((input, result));
+    }
+    Err(nom::Err::Failure(ParseError::expected(rest, "`;`".to_string())))
+  }
+
+  // item = function_definition | if_else_statements | while | for | return_statement | top_level_statement ;
+  // A `return` outside of any function ends the program, with its value
+  pub fn item(input: Input) -> IResult<Input, Node, ParseError> {
+    context("a function definition, statement or expression", alt((function_definition, if_else_statements, while_statement, for_statement, return_statement, top_level_statement)))(input)
+  }
+
+  // program = {item} ;
+  pub fn program_input(input: Input) -> IResult<Input, Node, ParseError> {
+    let start = input;
+    let (input, result) = many1(preceded(multispace0, item))(input)?; // many1 applies parser 1 or more times to call item function, skipping the whitespace before each one, which returns the output of the function in result as well as the now partially consumed input in input
+    Ok((input, Node::Program{ children: result, span: Span::between(start, input)}))
+  }
+
+  // This is the lenient entry point, it takes the plain source text and hands back whatever is left over as plain text too.
+  pub fn program(source: &str) -> IResult<&str, Node, ParseError> {
+    let (input, result) = program_input(Input::new(source))?; // wraps the source so the parsers can track positions in it
+    Ok((input.fragment(), result))
   }
 
-  // program = {function_definition | if_else_statements | function_call | statement | variable_define | conditional_exp | expression} ;
-  pub fn program(input: &str) -> IResult<&str, Node> {
-    let (input, result) = many1(alt((function_definition, if_else_statements, function_call, statement, variable_define, conditional_exp, expression)))(input)?;  // many1 applies parser 1 or more times to take a list of functions function_definition, if_else_statements, function_call, statement, variable_define, conditional_exp, expression and returns it to result
-    Ok((input, Node::Program{ children: result}))   
+  // The strict entry point: the whole source has to be a program, apart from whitespace at the end. When parsing
+  // stops early the item it stopped on is parsed again on its own, so the error says what went wrong with it.
+  pub fn parse_program(source: &str) -> Result<Node, ParseError> {
+    let start = Input::new(source);
+    let (input, tree) = program_input(start).map_err(into_parse_error)?;
+    let (rest, _) = multispace0::<_, ParseError>(input).map_err(into_parse_error)?;
+    if rest.fragment().is_empty() {
+      return Ok(tree);
+    }
+    match item(rest) {
+      Err(error) => Err(into_parse_error(error)),
+      Ok(_) => Err(ParseError::expected(rest, "end of input".to_string())),
+    }
+  }
+
+  // Takes the error out of nom's wrapper, the parsers only ever work on complete input so there's always one
+  fn into_parse_error(error: nom::Err<ParseError>) -> ParseError {
+    match error {
+      nom::Err::Error(error) | nom::Err::Failure(error) => error,
+      nom::Err::Incomplete(_) => ParseError { span: Span::default(), expected: "more input".to_string(), found: "end of input".to_string() },
+    }
   }
-  
\ No newline at end of file
@@ -1,37 +1,55 @@
 extern crate asalang;
 extern crate nom;
 
-use asalang::{program, Node, Value, Runtime};
+use asalang::{parse_program, program, AsaError, Value};
 use asalang::interpreter::start_interpreter;
-use nom::IResult;
 
 macro_rules! test {
   ($func:ident, $test:tt, $expected:expr) => (
     #[test]
     fn $func() -> Result<(),String> {
-      match program($test) {
-        Ok((input, p)) => {
-          let mut runtime = Runtime::new();
-          assert_eq!(input, "");
-          assert_eq!(start_interpreter(&p), $expected); 
+      match parse_program($test) {
+        Ok(p) => {
+          assert_eq!(start_interpreter(&p).map_err(|error| error.with_span(None)), $expected); 
           Ok(())
         },
-        Err(e) => Err(format!("{:?}",e)),
+        Err(e) => Err(e.to_string()),
       }
     }
   )
 }
 
+// Checks the full error message, including where in the source it points
+macro_rules! test_error {
+  ($func:ident, $test:tt, $expected:expr) => (
+    #[test]
+    fn $func() {
+      let p = parse_program($test).unwrap();
+      assert_eq!(start_interpreter(&p).unwrap_err().to_string(), $expected);
+    }
+  )
+}
+
+// Checks the message for source that doesn't parse
+macro_rules! test_parse_error {
+  ($func:ident, $test:tt, $expected:expr) => (
+    #[test]
+    fn $func() {
+      assert_eq!(parse_program($test).unwrap_err().to_string(), $expected);
+    }
+  )
+}
+
 
 
 test!(numeric, r#"123"#, Ok(Value::Number(123))); 
-test!(identifier, r#"x"#, Err("Undefined variable".to_string())); 
+test!(identifier, r#"x"#, Err(AsaError::UndefinedVariable { name: "x".to_string(), span: None })); 
 test!(string, r#""hello world""#, Ok(Value::String("hello world".to_string()))); 
 test!(bool_true, r#"true"#, Ok(Value::Bool(true))); 
 test!(bool_false, r#"false"#, Ok(Value::Bool(false))); 
-test!(function_call, r#"foo()"#, Err("Undefined function".to_string())); 
-test!(function_call_one_arg, r#"foo(a)"#, Err("Undefined function".to_string())); 
-test!(function_call_more_args, r#"foo(a,b,c)"#, Err("Undefined function".to_string())); 
+test!(function_call, r#"foo()"#, Err(AsaError::UndefinedFunction { name: "foo".to_string(), span: None })); 
+test!(function_call_one_arg, r#"foo(a)"#, Err(AsaError::UndefinedFunction { name: "foo".to_string(), span: None })); 
+test!(function_call_more_args, r#"foo(a,b,c)"#, Err(AsaError::UndefinedFunction { name: "foo".to_string(), span: None })); 
 test!(variable_define, r#"let x = 123;"#, Ok(Value::Number(123))); 
 test!(variable_init, r#"let x = 1;"#, Ok(Value::Number(1))); 
 test!(variable_bool, r#"let bool = true;"#, Ok(Value::Bool(true))); 
@@ -46,8 +64,8 @@ test!(math_exponent, r#"2 ^ 4"#, Ok(Value::Number(16)));
 test!(math_more_terms, r#"10 + 2*6"#, Ok(Value::Number(22))); 
 test!(math_more_terms_paren, r#"((10+2)*6)/4"#, Ok(Value::Number(18))); 
 test!(assign_math, r#"let x = 1 + 1;"#, Ok(Value::Number(2)));
-test!(assign_function, r#"let x = foo();"#, Err("Undefined function".to_string())); 
-test!(assign_function_arguments, r#"let x = foo(a,b,c);"#, Err("Undefined function".to_string())); 
+test!(assign_function, r#"let x = foo();"#, Err(AsaError::UndefinedFunction { name: "foo".to_string(), span: None })); 
+test!(assign_function_arguments, r#"let x = foo(a,b,c);"#, Err(AsaError::UndefinedFunction { name: "foo".to_string(), span: None })); 
 test!(define_function, r#"fn main(){return foo();} fn foo(){return 5;}"#, Ok(Value::Number(5))); 
 test!(define_function_args, r#"fn main(){return foo(1,2,3);} fn foo(a,b,c){return a+b+c;}"#, Ok(Value::Number(6))); 
 test!(define_function_more_statement, r#"fn main() { 
@@ -77,7 +95,7 @@ test!(variable_def_new, r#"let z = 99;"#, Ok(Value::Number(99))); // tests varia
 test!(math_exponent_new, r#"3 ^ 2"#, Ok(Value::Number(9))); // tests exponents
 test!(conditional_ex1, r#"5 < 7"#, Ok(Value::Bool(true))); 
 test!(conditional_ex2, r#"11 + 6 * 2 < 5 * 2 - 3"#, Ok(Value::Bool(false))); 
-test!(conditional_ex3, r#"7 > true"#, Err("Cannot compare these two values".to_string())); 
+test!(conditional_ex3, r#"7 > true"#, Err(AsaError::TypeMismatch { expected: "number".to_string(), found: Value::Bool(true), span: None })); 
 test!(if_else_ex1, r#"if 1 < 2 {
   let x = 9;
   return true;
@@ -97,6 +115,382 @@ test!(if_else_ex1, r#"if 1 < 2 {
     "#, Ok(Value::Bool(true))); 
     test!(if_else_ex3, r#"if 4 > 3 {return true;} else if 7 == 9 {return false;} else {return true;}
       "#, Ok(Value::Bool(true))); 
+test!(function_too_few_args, r#"fn main(){return foo(1);} fn foo(a,b){return a+b;}"#, Err(AsaError::ArityMismatch { name: "foo".to_string(), expected: 2, found: 1, span: None }));
+test!(function_too_many_args, r#"fn main(){return foo(1,2);} fn foo(){return 5;}"#, Err(AsaError::ArityMismatch { name: "foo".to_string(), expected: 0, found: 2, span: None }));
+test!(math_type_error, r#"fn main() { let s = "hi"; return s * 2; }"#, Err(AsaError::TypeMismatch { expected: "number".to_string(), found: Value::String("hi".to_string()), span: None }));
+test_error!(undefined_variable_location, r#"fn main() {
+  let x = 1;
+  return x + y;
+}"#, "3:14: undefined variable 'y'");
+test_error!(math_type_error_location, r#"fn main() { let s = "hi"; return s * 2; }"#, "1:34: expected number, found string hi");
+test_error!(arity_error_message, r#"fn main(){return foo(1);} fn foo(a,b){return a+b;}"#, "1:18: function 'foo' takes 2 arguments but 1 was given");
 
+#[test]
+fn error_underlines_source() {
+  let source = "fn main() {\n  return 1 + foo(2);\n}";
+  let p = parse_program(source).unwrap();
+  let error = start_interpreter(&p).unwrap_err();
+  assert_eq!(error.render(source), "2:14: undefined function 'foo'\n  |\n2 |   return 1 + foo(2);\n  |              ^^^^^^");
+}
 
+test!(name_starting_with_true, r#"let t = 1; let trueish = 2; t + trueish"#, Ok(Value::Number(3)));
+test!(name_starting_with_false, r#"fn main() { let falsey = 4; return falsey * 2; }"#, Ok(Value::Number(8)));
+test_parse_error!(parse_error_bool_as_name, "let true = 5;", "1:5: expected a variable name, found `true`");
+test_parse_error!(parse_error_keyword_as_name, "fn main() {\n  let else = 2;\n}", "2:7: expected a variable name, found `else`");
+test_parse_error!(parse_error_missing_brace, "if 7 > 3 {\n  return true;\n else {\n  return false;\n}", "3:2: expected `}` to close the `if` block opened at 1:10, found `else`");
+test_parse_error!(parse_error_unclosed_call, "fn main() {\n  return foo(1;\n}", "2:15: expected `)` to close the call to `foo` opened at 2:13, found `;`");
+test_parse_error!(parse_error_missing_let_value, "let x = ;", "1:9: expected an expression, found `;`");
+test_parse_error!(parse_error_trailing_input, "fn main() {\n  return 1;\n}\nlet x = 1 +* 2;", "4:12: expected a value, found `*`");
+test_parse_error!(parse_error_empty_program, "  \n", "2:1: expected a function definition, statement or expression, found end of input");
+test_parse_error!(parse_error_top_level_break, "break;", "1:1: expected a function definition, statement or expression, found `break`");
+test_parse_error!(parse_error_string_with_symbol, "let x = \"a-b\";", "1:11: expected `\"` to close the string opened at 1:9, found `-`");
+test_parse_error!(parse_error_empty_string, "let x = \"\";", "1:10: expected a letter, digit or space, found `\"`");
+test_parse_error!(parse_error_dangling_plus, "fn main() { x + }", "1:17: expected a value, found `}`");
+test_parse_error!(parse_error_dangling_times, "let y = 2 * ;", "1:13: expected a value, found `;`");
+test_parse_error!(parse_error_dangling_power, "let y = 2 ^ ;", "1:13: expected a value, found `;`");
+test_parse_error!(parse_error_let_missing_semicolon, "fn main() { let x = 1 }", "1:23: expected `;`, found `}`");
+test_parse_error!(parse_error_top_level_missing_semicolon, "let x = 10 y = 3", "1:12: expected `;`, found `y`");
+test_parse_error!(parse_error_if_operand_missing_semicolon, "let s = if true { 1 } else { 2 } + 5; s", "1:34: expected `;`, found `+`");
+test_parse_error!(parse_error_float_missing_exponent, "let y = 1.5e;", "1:12: expected `;`, found `e`");
+test!(top_level_semicolon_at_end_of_line, "let x = 1
+x = x + 1
+x", Ok(Value::Number(2)));
+test!(trailing_whitespace, "fn main() {\n  return 1;\n}\n\n  ", Ok(Value::Number(1)));
 
+#[test]
+fn lenient_program_returns_leftover() {
+  let (rest, _) = program("1 + 2; )").unwrap();
+  assert_eq!(rest, " )");
+}
+test!(while_false_skips_body, r#"fn main() { let x = 1; while x > 2 { let x = 5; } return x; }"#, Ok(Value::Number(1)));
+test!(while_break, r#"fn main() { let x = 1; while x < 2 { x = 5; break; } return x; }"#, Ok(Value::Number(5)));
+test!(name_starting_with_break, r#"fn main() { let breakfast = 1; breakfast }"#, Ok(Value::Number(1)));
+test!(name_starting_with_continue, r#"fn main() { let continued = 1; continued + 1 }"#, Ok(Value::Number(2)));
+test!(while_break_skips_rest_of_body, r#"fn main() { let x = 1; while x < 2 { break; let x = 5; } return x; }"#, Ok(Value::Number(1)));
+test!(while_break_inner_loop_only, r#"fn main() {
+  let x = 1;
+  while x < 2 {
+    while true == true {
+      break;
+    }
+    x = 7;
+    break;
+  }
+  return x;
+}"#, Ok(Value::Number(7)));
+test_error!(break_outside_loop, "fn main() { break; }", "1:13: `break` outside of a loop");
+test!(for_exclusive_range, r#"fn main() { let x = 0; for i in 1..3 { x = i; } return x; }"#, Ok(Value::Number(2)));
+test!(for_inclusive_range, r#"fn main() { let x = 0; for i in 1..=3 { x = i; } return x; }"#, Ok(Value::Number(3)));
+test!(for_bounds_are_expressions, r#"fn main() { let n = 2; let x = 0; for i in n * 2..n ^ 3 { x = i; } return x; }"#, Ok(Value::Number(7)));
+test!(for_empty_range, r#"fn main() { let x = 0; for i in 3..3 { let x = i; } return x; }"#, Ok(Value::Number(0)));
+test!(for_break, r#"fn main() { let x = 0; for i in 1..10 { x = i; break; } return x; }"#, Ok(Value::Number(1)));
+test!(for_restores_loop_variable, r#"fn main() { let i = 9; for i in 0..3 { let x = i; } return i; }"#, Ok(Value::Number(9)));
+test_error!(for_range_type_error, r#"fn main() { let s = "a"; for i in 0..s { break; } return 1; }"#, "1:38: expected number, found string a");
+test_parse_error!(parse_error_for_missing_range, "for i in 0 { }", "1:12: expected `..` or `..=`, found `{`");
+test!(assignment, r#"fn main() { let x = 1; x = x + 4; return x; }"#, Ok(Value::Number(5)));
+test!(assignment_changes_type, r#"fn main() { let x = 1; x = "one"; return x; }"#, Ok(Value::String("one".to_string())));
+test!(compound_assignment, r#"fn main() { let x = 10; x += 5; x -= 3; x *= 2; x /= 4; return x; }"#, Ok(Value::Number(6)));
+test!(assignment_undeclared, r#"fn main() { y = 1; return 1; }"#, Err(AsaError::UndefinedVariable { name: "y".to_string(), span: None }));
+test_error!(compound_assignment_type_error, r#"fn main() { let s = "a"; s += 1; return s; }"#, "1:26: expected number, found string a");
+test!(while_counter, r#"fn main() {
+  let total = 0;
+  let i = 0;
+  while i < 10 {
+    i += 1;
+    total += i;
+  }
+  return total;
+}"#, Ok(Value::Number(55)));
+test!(for_continue, r#"fn main() { let total = 0; for i in 0..5 { total += 1; continue; total += 100; } return total; }"#, Ok(Value::Number(5)));
+test!(while_continue, r#"fn main() { let i = 0; let skipped = 0; while i < 3 { i += 1; continue; skipped = 1; } return skipped; }"#, Ok(Value::Number(0)));
+test!(for_loop_variable_reassigned, r#"fn main() { let count = 0; for i in 0..3 { i = 10; count += 1; } return count; }"#, Ok(Value::Number(3)));
+test!(equality_is_not_assignment, r#"fn main() { let x = 1; while x == 1 { x = 2; } return x; }"#, Ok(Value::Number(2)));
+test!(block_let_shadows_outer, r#"fn main() { let x = 1; for i in 0..3 { let x = 10; x += i; } return x; }"#, Ok(Value::Number(1)));
+test!(block_assignment_updates_outer, r#"fn main() { let x = 1; for i in 0..3 { x += i; } return x; }"#, Ok(Value::Number(4)));
+test!(block_let_does_not_leak, r#"fn main() { for i in 0..3 { let y = i; } return y; }"#, Err(AsaError::UndefinedVariable { name: "y".to_string(), span: None }));
+test!(loop_variable_does_not_leak, r#"fn main() { for i in 0..3 { let y = i; } return i; }"#, Err(AsaError::UndefinedVariable { name: "i".to_string(), span: None }));
+test!(nested_blocks_walk_outwards, r#"fn main() {
+  let total = 0;
+  for i in 1..=3 {
+    let step = i;
+    for j in 0..2 {
+      total += step;
+    }
+  }
+  return total;
+}"#, Ok(Value::Number(12)));
+test!(each_pass_gets_fresh_scope, r#"fn main() { let n = 0; while n < 3 { n += 1; let seen = n; } return n; }"#, Ok(Value::Number(3)));
+test!(function_cannot_see_caller_locals, r#"fn main() { let secret = 1; return peek(); } fn peek() { return secret; }"#, Err(AsaError::UndefinedVariable { name: "secret".to_string(), span: None }));
+test!(global_read_from_function, r#"let limit = 10; fn main() { return limit * 2; }"#, Ok(Value::Number(20)));
+test!(global_read_from_helper, r#"let base = 3; fn add(x) { return x + base; } fn main() { return add(4); }"#, Ok(Value::Number(7)));
+test!(global_uses_later_function, r#"let x = double(4); fn double(n) { return n * 2; } fn main() { return x; }"#, Ok(Value::Number(8)));
+test!(global_shadowed_by_local, r#"let x = 1; fn main() { let x = 2; return x; }"#, Ok(Value::Number(2)));
+test!(global_shadowed_by_parameter, r#"let x = 1; fn show(x) { return x; } fn main() { return show(5); }"#, Ok(Value::Number(5)));
+test!(global_assigned_from_function, r#"let count = 0; fn bump() { count += 1; return count; } fn main() { let first = bump(); return bump(); }"#, Ok(Value::Number(2)));
+test!(global_expression_item, r#"let x = 4; x * 2"#, Ok(Value::Number(8)));
+test!(globals_without_main, r#"let x = 4; let y = x + 1;"#, Ok(Value::Number(5)));
+
+#[test]
+fn runtime_without_frames_reads_globals() {
+  let mut runtime = asalang::Runtime::new();
+  runtime.eval(&parse_program("let x = 2;").unwrap()).unwrap();
+  assert_eq!(runtime.eval(&parse_program("x + 1").unwrap()), Ok(Value::Number(3)));
+  assert_eq!(runtime.eval(&parse_program("y").unwrap()).map_err(|error| error.with_span(None)), Err(AsaError::UndefinedVariable { name: "y".to_string(), span: None }));
+}
+test!(script_runs_every_item, r#"let x = 1; let y = x + 1; y"#, Ok(Value::Number(2)));
+test!(script_items_share_state, r#"let total = 0;
+for i in 1..=4 {
+  total += i;
+}
+total * 10"#, Ok(Value::Number(100)));
+test!(script_error_in_earlier_item, r#"let x = missing; 1 + 1"#, Err(AsaError::UndefinedVariable { name: "missing".to_string(), span: None }));
+test!(script_then_main, r#"let greeting = "hi"; fn main() { return greeting; } 42"#, Ok(Value::String("hi".to_string())));
+test!(script_calls_functions, r#"fn double(n) { return n * 2; } let x = double(3); double(x)"#, Ok(Value::Number(12)));
+test!(script_block_locals_hidden_from_functions, r#"fn f() { return y; } if true { let y = 5; f() } else { 0 }"#, Err(AsaError::UndefinedVariable { name: "y".to_string(), span: None }));
+test!(script_loop_variable_hidden_from_functions, r#"fn g() { return i * 10; } let t = 0; for i in 1..3 { t += g(); } t"#, Err(AsaError::UndefinedVariable { name: "i".to_string(), span: None }));
+test!(script_block_assigns_global, r#"let x = 1; fn get() { return x; } if true { x = 2; } get()"#, Ok(Value::Number(2)));
+test!(script_expression_statement, r#"let c = 0; fn bump() { c += 1; } bump(); bump(); c"#, Ok(Value::Number(2)));
+test!(script_call_then_item, "let c = 0; fn bump() { c += 1; }\nbump()\nc", Ok(Value::Number(1)));
+test!(script_return, r#"return 1;"#, Ok(Value::Number(1)));
+test!(script_return_ends_the_script, r#"if true { return 1; } return 2; 3"#, Ok(Value::Number(1)));
+test!(script_return_skips_main, r#"fn main() { return 2; } return 1;"#, Ok(Value::Number(1)));
+test!(if_in_function_body, r#"fn sign(x) {
+  if x < 0 {
+    return 0 - 1;
+  } else if x == 0 {
+    return 0;
+  } else {
+    return 1;
+  }
+}
+fn main() { return sign(5) + sign(0) * 10 + sign(0 - 3) * 100; }"#, Ok(Value::Number(-99)));
+test!(early_return_skips_rest, r#"fn main() {
+  let x = 1;
+  if x == 1 {
+    return 10;
+  } else {
+    x = 2;
+  }
+  return 20;
+}"#, Ok(Value::Number(10)));
+test!(if_without_return_falls_through, r#"fn main() {
+  let x = 1;
+  if x == 1 {
+    x += 5;
+  } else {
+    x = 0;
+  }
+  return x;
+}"#, Ok(Value::Number(6)));
+test!(return_from_nested_loops, r#"fn find() {
+  for i in 1..10 {
+    for j in 1..10 {
+      if i * j == 12 {
+        return i * 100 + j;
+      } else {
+        continue;
+      }
+    }
+  }
+  return 0;
+}
+fn main() { return find(); }"#, Ok(Value::Number(206)));
+test!(break_from_if_inside_loop, r#"fn main() {
+  let total = 0;
+  for i in 0..100 {
+    if i == 5 {
+      break;
+    } else {
+      total += i;
+    }
+  }
+  return total;
+}"#, Ok(Value::Number(10)));
+test!(continue_from_if_inside_loop, r#"fn main() {
+  let total = 0;
+  let i = 0;
+  while i < 10 {
+    i += 1;
+    if i == 3 {
+      continue;
+    } else {
+      total += i;
+    }
+  }
+  return total;
+}"#, Ok(Value::Number(52)));
+test!(expression_statement, r#"let count = 0; fn bump() { count += 1; return count; } fn main() { bump(); bump(); return count; }"#, Ok(Value::Number(2)));
+test!(function_without_return, r#"fn main() { let x = 1; x + 2; }"#, Ok(Value::Number(3)));
+test!(if_block_scope, r#"fn main() { let x = 1; if x == 1 { let x = 2; } else { let x = 3; } return x; }"#, Ok(Value::Number(1)));
+test_error!(continue_outside_loop, "fn main() {\n  if 1 < 2 { continue; } else { return 1; }\n}", "2:14: `continue` outside of a loop");
+test!(if_branch_taken, r#"if 1 < 2 { return 1; } else if 2 < 3 { return 2; } else { return 3; }"#, Ok(Value::Number(1)));
+test!(else_if_branch_taken, r#"if 2 < 1 { return 1; } else if 2 < 3 { return 2; } else { return 3; }"#, Ok(Value::Number(2)));
+test!(first_true_else_if_taken, r#"if 2 < 1 { return 1; } else if 3 < 2 { return 2; } else if 1 == 1 { return 3; } else if 2 == 2 { return 4; } else { return 5; }"#, Ok(Value::Number(3)));
+test!(else_branch_taken, r#"if 2 < 1 { return 1; } else if 3 < 2 { return 2; } else { return 3; }"#, Ok(Value::Number(3)));
+test!(only_taken_branch_runs, r#"let hits = 0;
+if 1 == 1 { hits += 1; } else if 2 == 2 { hits += 10; } else { hits += 100; }
+hits"#, Ok(Value::Number(1)));
+test!(if_branch_value_without_return, r#"let x = 4; if x > 3 { x * 2; } else { x; }"#, Ok(Value::Number(8)));
+test!(if_chain_in_function_each_branch, r#"fn grade(n) {
+  if n > 89 {
+    return 4;
+  } else if n > 79 {
+    return 3;
+  } else if n > 69 {
+    return 2;
+  } else {
+    return 0;
+  }
+}
+fn main() { return grade(95) * 1000 + grade(85) * 100 + grade(75) * 10 + grade(10); }"#, Ok(Value::Number(4320)));
+test_error!(break_outside_loop_at_top_level, "if 1 < 2 { break; } else { return 1; }", "1:12: `break` outside of a loop");
+test!(if_without_else_taken, "if 7 > 3 {\n  return true;\n}\n", Ok(Value::Bool(true)));
+test!(if_without_else_not_taken, "if 7 < 3 { return true; }", Ok(Value::Unit));
+test!(else_if_without_else_not_taken, "if 7 < 3 { return 1; } else if 7 == 3 { return 2; }", Ok(Value::Unit));
+test!(if_guard_in_function, r#"fn abs(x) { if x < 0 { return 0 - x; } return x; } fn main() { return abs(0 - 5) + abs(3); }"#, Ok(Value::Number(8)));
+test!(if_without_else_updates_variable, r#"let x = 1; if x == 1 { x = 5; } x"#, Ok(Value::Number(5)));
+test!(loop_has_no_value, r#"let x = 0; while x < 3 { x += 1; }"#, Ok(Value::Unit));
+test!(empty_block_has_no_value, r#"fn main() { }"#, Ok(Value::Unit));
+test!(if_expression_in_let, r#"let x = 0 - 3; let sign = if x < 0 { 0 - 1 } else { 1 }; sign"#, Ok(Value::Number(-1)));
+test!(if_expression_else_if, r#"fn sign(x) { return if x < 0 { 0 - 1 } else if x == 0 { 0 } else { 1 }; } fn main() { return sign(0 - 4) * 100 + sign(0) * 10 + sign(9); }"#, Ok(Value::Number(-99)));
+test!(if_expression_as_argument, r#"fn double(x) { return x * 2; } fn main() { return double(if 1 < 2 { 4 } else { 5 }); }"#, Ok(Value::Number(8)));
+test!(if_expression_in_parentheses, r#"let s = (if true { 1 } else { 2 }) + 5; s"#, Ok(Value::Number(6)));
+test!(if_expression_as_operand, r#"fn main() { return 10 * (if 1 > 2 { 1 } else { 2 }) - 1; }"#, Ok(Value::Number(19)));
+test!(if_expression_in_assignment, r#"let x = 1; x = if x == 1 { let y = 10; y + 1 } else { 0 }; x"#, Ok(Value::Number(11)));
+test!(block_value_without_semicolon, r#"fn main() { let x = 2; x * 3 }"#, Ok(Value::Number(6)));
+test_parse_error!(parse_error_if_expression_without_else, "let sign = if 1 < 0 { 1 };", "1:26: expected an `else` block, found `;`");
+test_error!(return_inside_if_expression, "fn main() {\n  let x = if 1 < 2 { return 1; } else { 2 };\n  return x;\n}", "2:11: `return` inside an `if` expression");
+test!(logical_and, r#"let x = 5; if x > 1 && x < 10 { 1 } else { 0 }"#, Ok(Value::Number(1)));
+test!(logical_or, r#"let x = 5; if x < 1 || x == 5 { 1 } else { 0 }"#, Ok(Value::Number(1)));
+test!(logical_not, r#"if !(1 > 2) { 1 } else { 0 }"#, Ok(Value::Number(1)));
+test!(logical_and_binds_tighter_than_or, r#"if true || false && false { 1 } else { 0 }"#, Ok(Value::Number(1)));
+test!(logical_not_applies_to_comparison, r#"let x = 3; if !x > 5 { 1 } else { 0 }"#, Ok(Value::Number(1)));
+test!(logical_and_short_circuits, r#"fn boom() { return 1 / 0; } if 1 > 2 && boom() == 1 { 1 } else { 0 }"#, Ok(Value::Number(0)));
+test!(logical_or_short_circuits, r#"let hits = 0; fn hit() { hits += 1; return true; } if 1 < 2 || hit() { hits } else { 0 - 1 }"#, Ok(Value::Number(0)));
+test!(logical_in_while, r#"let i = 0; let done = false; while i < 10 && !done { i += 1; if i == 4 { done = true; } } i"#, Ok(Value::Number(4)));
+test_error!(logical_operand_not_bool, "if 1 && true { 1 } else { 0 }", "1:4: expected bool, found number 1");
+test_error!(logical_not_operand_not_bool, "if !5 { 1 }", "1:5: expected bool, found number 5");
+test!(comparison_chain_left_assoc, r#"1 > 3 == true"#, Ok(Value::Bool(false)));
+test!(comparison_chain_three_operators, r#"1 < 2 == false != true"#, Ok(Value::Bool(true)));
+test!(comparison_chain_matches_grouping, r#"if 2 > 1 == false { 1 } else if (2 > 1) == false { 2 } else { 3 }"#, Ok(Value::Number(3)));
+test!(comparison_greater_or_equal, r#"let n = 90; if n >= 90 { 1 } else { 0 }"#, Ok(Value::Number(1)));
+test!(comparison_less_or_equal, r#"2 <= 2"#, Ok(Value::Bool(true)));
+test_error!(comparison_chain_compares_bool, "3 > 2 > 1", "1:1: expected bool, found number 1");
+test_parse_error!(parse_error_comparison_missing_operand, "if 1 < { 1 }", "1:8: expected a value to compare, found `{`");
+test!(comparison_in_let, r#"let a = 1; let b = 3; let ok = a + 1 < b; ok"#, Ok(Value::Bool(true)));
+test!(comparison_in_return, r#"fn same(x,y) { return x == y; } fn main() { return same(2,2); }"#, Ok(Value::Bool(true)));
+test!(comparison_as_argument, r#"fn not(x) { return !x; } not(1 < 2 && 2 < 3)"#, Ok(Value::Bool(false)));
+test!(logical_in_let, r#"let x = 4; let inside = x > 0 && x < 10 || x == 100; inside"#, Ok(Value::Bool(true)));
+test!(call_then_operator_at_top_level, r#"fn one() { return 1; } one() + 2"#, Ok(Value::Number(3)));
+test!(string_equality, r#"let s = "a b" == "a b"; s"#, Ok(Value::Bool(true)));
+test!(boolean_in_math_is_type_error, r#"false + 9"#, Err(AsaError::TypeMismatch { expected: "number".to_string(), found: Value::Bool(false), span: None }));
+test!(comparison_of_different_types, r#""a" < 1"#, Err(AsaError::TypeMismatch { expected: "string".to_string(), found: Value::Number(1), span: None }));
+test!(negative_literal, r#"let t = -3; t"#, Ok(Value::Number(-3)));
+test!(unary_minus_variable, r#"let x = 3; -x"#, Ok(Value::Number(-3)));
+test!(unary_minus_binds_tighter_than_power, r#"-2 ^ 2"#, Ok(Value::Number(4)));
+test!(unary_minus_parenthesized, r#"-(2 + 3) * 2"#, Ok(Value::Number(-10)));
+test!(subtract_negative, r#"5 - -3"#, Ok(Value::Number(8)));
+test!(unary_plus, r#"let x = 3; +4 + +x"#, Ok(Value::Number(7)));
+test!(unary_plus_float, r#"+1.5"#, Ok(Value::Float(1.5)));
+test!(smallest_number_literal, r#"-9223372036854775808"#, Ok(Value::Number(i64::MIN)));
+test!(negate_smallest_number_promotes, r#"-(-9223372036854775808)"#, Ok(Value::BigInt("9223372036854775808".parse().unwrap())));
+test_error!(negate_bool, "-true", "1:2: expected number, found bool true");
+test!(modulo, r#"17 % 5"#, Ok(Value::Number(2)));
+test!(modulo_same_precedence_as_multiply, r#"2 + 7 % 4"#, Ok(Value::Number(5)));
+test!(floor_division_negative_dividend, r#"-7 / 2"#, Ok(Value::Number(-4)));
+test!(floor_division_negative_divisor, r#"7 / -2"#, Ok(Value::Number(-4)));
+test!(floor_division_both_negative, r#"-7 / -2"#, Ok(Value::Number(3)));
+test!(modulo_negative_dividend, r#"-7 % 2"#, Ok(Value::Number(1)));
+test!(modulo_negative_divisor, r#"7 % -2"#, Ok(Value::Number(-1)));
+test!(division_and_modulo_agree, r#"let a = -17; let b = 5; (a / b) * b + a % b == a"#, Ok(Value::Bool(true)));
+test!(modulo_smallest_number, r#"-9223372036854775808 % -1"#, Ok(Value::Number(0)));
+test!(modulo_assign, r#"let x = 17; x %= 5; x"#, Ok(Value::Number(2)));
+test_error!(division_by_zero, "6 / 0", "1:1: division by zero");
+test_error!(modulo_by_zero, "let x = 0; 6 % x", "1:12: division by zero");
+test!(division_promotes, r#"-9223372036854775808 / -1"#, Ok(Value::BigInt("9223372036854775808".parse().unwrap())));
+test!(add_promotes, r#"9223372036854775807 + 1"#, Ok(Value::BigInt("9223372036854775808".parse().unwrap())));
+test!(subtract_promotes, r#"-9223372036854775808 - 1"#, Ok(Value::BigInt("-9223372036854775809".parse().unwrap())));
+test!(multiply_promotes, r#"10000000000 * 10000000000"#, Ok(Value::BigInt("100000000000000000000".parse().unwrap())));
+test!(power_promotes, r#"2 ^ 63"#, Ok(Value::BigInt("9223372036854775808".parse().unwrap())));
+test_error!(negative_exponent, "2 ^ -1", "1:1: negative exponent -1 in '^'");
+test!(compound_assign_promotes, r#"let x = 9223372036854775807; x += 1; x"#, Ok(Value::BigInt("9223372036854775808".parse().unwrap())));
+test!(power_zero_exponent, r#"5 ^ 0"#, Ok(Value::Number(1)));
+test!(power_largest_result, r#"-2 ^ 63"#, Ok(Value::Number(i64::MIN)));
+test!(number_wider_than_32_bits, r#"99999999999"#, Ok(Value::Number(99999999999)));
+test!(big_literal, r#"99999999999999999999"#, Ok(Value::BigInt("99999999999999999999".parse().unwrap())));
+test!(big_negative_literal, r#"-99999999999999999999 + 1"#, Ok(Value::BigInt("-99999999999999999998".parse().unwrap())));
+test!(big_factorial, r#"let f = 1; for i in 1..=25 { f *= i; } f"#, Ok(Value::BigInt("15511210043330985984000000".parse().unwrap())));
+test!(big_demotes_when_it_fits, r#"2 ^ 64 / 2 ^ 60"#, Ok(Value::Number(16)));
+test!(big_floor_division, r#"-(2 ^ 70) / 3"#, Ok(Value::BigInt("-393530540239137101142".parse().unwrap())));
+test!(big_modulo, r#"2 ^ 64 % -7"#, Ok(Value::Number(-5)));
+test!(big_compare_number, r#"2 ^ 64 > 9223372036854775807"#, Ok(Value::Bool(true)));
+test!(big_equal, r#"2 ^ 64 == 18446744073709551616"#, Ok(Value::Bool(true)));
+test!(big_float_promotion, r#"2 ^ 64 * 0.5"#, Ok(Value::Float(9223372036854775808.0)));
+test!(big_power_of_one, r#"(-1) ^ 99999999999999999999"#, Ok(Value::Number(-1)));
+test_error!(big_division_by_zero, "2 ^ 64 / 0", "1:1: division by zero");
+test_error!(big_negative_exponent, "2 ^ -99999999999999999999", "1:1: negative exponent -99999999999999999999 in '^'");
+test_error!(power_too_big, "3 ^ 99999999", "1:1: arithmetic overflow in '^'");
+test_error!(big_loop_bound, "for i in 0..2 ^ 64 { }", "1:13: can't convert number 18446744073709551616 to a loop counter");
+
+#[test]
+fn checked_arithmetic() {
+  let mut runtime = asalang::Runtime::with_arithmetic(asalang::Arithmetic::Checked);
+  let mut eval = |source| runtime.eval(&parse_program(source).unwrap()).map_err(|error| error.to_string());
+  assert_eq!(eval("9223372036854775807 + 1"), Err("1:1: arithmetic overflow in '+'".to_string()));
+  assert_eq!(eval("-9223372036854775808 - 1"), Err("1:1: arithmetic overflow in '-'".to_string()));
+  assert_eq!(eval("10000000000 * 10000000000"), Err("1:1: arithmetic overflow in '*'".to_string()));
+  assert_eq!(eval("-9223372036854775808 / -1"), Err("1:1: arithmetic overflow in '/'".to_string()));
+  assert_eq!(eval("2 ^ 63"), Err("1:1: arithmetic overflow in '^'".to_string()));
+  assert_eq!(eval("-(-9223372036854775808)"), Err("1:1: arithmetic overflow in '-'".to_string()));
+  assert_eq!(eval("let x = 9223372036854775807; x += 1;"), Err("1:30: arithmetic overflow in '+'".to_string()));
+  assert_eq!(eval("99999999999999999999 - 99999999999999999998"), Err("1:1: arithmetic overflow in '99999999999999999999'".to_string()));
+  assert_eq!(eval("let x = 99999999999999999999;"), Err("1:9: arithmetic overflow in '99999999999999999999'".to_string()));
+}
+
+#[test]
+fn wrapping_arithmetic() {
+  let mut runtime = asalang::Runtime::with_arithmetic(asalang::Arithmetic::Wrapping);
+  let mut eval = |source| runtime.eval(&parse_program(source).unwrap()).map_err(|error| error.with_span(None));
+  assert_eq!(eval("9223372036854775807 + 1"), Ok(Value::Number(i64::MIN)));
+  assert_eq!(eval("10000000000 * 10000000000"), Ok(Value::Number(7766279631452241920)));
+  assert_eq!(eval("-(-9223372036854775808)"), Ok(Value::Number(i64::MIN)));
+  assert_eq!(eval("-9223372036854775808 / -1"), Ok(Value::Number(i64::MIN)));
+  assert_eq!(eval("3 ^ 99999999999"), Ok(Value::Number(-2062592170169791829)));
+  assert_eq!(eval("99999999999999999999 + 0"), Ok(Value::Number(7766279631452241919)));
+  assert_eq!(eval("99999999999999999999"), Ok(Value::Number(7766279631452241919)));
+  assert_eq!(eval("6 / 0"), Err(AsaError::DivisionByZero { span: None }));
+  assert_eq!(eval("2 ^ -1"), Err(AsaError::NegativeExponent { exponent: Value::Number(-1), span: None }));
+}
+test!(divide_left_assoc, r#"8 / 4 / 2"#, Ok(Value::Number(1)));
+test!(multiply_divide_left_assoc, r#"100 / 10 * 2"#, Ok(Value::Number(20)));
+test!(modulo_multiply_left_assoc, r#"7 % 4 * 2"#, Ok(Value::Number(6)));
+test!(subtract_left_assoc, r#"2 - 3 - 4"#, Ok(Value::Number(-5)));
+test!(subtract_add_left_assoc, r#"1 - 2 + 3"#, Ok(Value::Number(2)));
+test!(power_right_assoc, r#"2 ^ 3 ^ 2"#, Ok(Value::Number(512)));
+test!(power_binds_tighter_than_multiply, r#"2 * 3 ^ 2"#, Ok(Value::Number(18)));
+test!(power_of_negative_exponent_chain, r#"2 ^ 2 ^ -1"#, Err(AsaError::NegativeExponent { exponent: Value::Number(-1), span: None }));
+test!(mixed_precedence, r#"1 + 2 * 3 ^ 2 ^ 1 - 8 / 2 / 2"#, Ok(Value::Number(17)));
+test!(float_literal, r#"1.5"#, Ok(Value::Float(1.5)));
+test!(float_literal_exponent, r#"3e8"#, Ok(Value::Float(3e8)));
+test!(float_literal_fraction_and_exponent, r#"-2.5e-3"#, Ok(Value::Float(-2.5e-3)));
+test!(range_is_not_float, r#"let total = 0; for i in 0..3 { total += i; } total"#, Ok(Value::Number(3)));
+test!(int_float_promotion, r#"1 + 1.5"#, Ok(Value::Float(2.5)));
+test!(float_division_keeps_fraction, r#"7.0 / 2"#, Ok(Value::Float(3.5)));
+test!(float_average, r#"let a = 1; let b = 2; let c = 4; float(a + b + c) / 3 > 2.3"#, Ok(Value::Bool(true)));
+test!(float_modulo_rounds_down, r#"-7.5 % 2"#, Ok(Value::Float(0.5)));
+test!(float_negative_exponent, r#"2.0 ^ -1"#, Ok(Value::Float(0.5)));
+test!(float_divide_by_zero, r#"1.0 / 0"#, Ok(Value::Float(f64::INFINITY)));
+test!(int_float_equal, r#"1 == 1.0"#, Ok(Value::Bool(true)));
+test!(nan_not_equal_to_itself, r#"let n = 0.0 / 0.0; n == n"#, Ok(Value::Bool(false)));
+test!(nan_unequal_to_itself, r#"let n = 0.0 / 0.0; n != n"#, Ok(Value::Bool(true)));
+test!(nan_not_ordered, r#"let n = 0.0 / 0.0; n < 1 || n > 1 || n <= 1 || n >= 1"#, Ok(Value::Bool(false)));
+test!(negate_float, r#"let x = 1.5; -x"#, Ok(Value::Float(-1.5)));
+test!(float_builtin, r#"float(2)"#, Ok(Value::Float(2.0)));
+test!(int_builtin_truncates, r#"int(-3.9)"#, Ok(Value::Number(-3)));
+test!(user_function_shadows_builtin, r#"fn int(x) { return 7; } int(1.5)"#, Ok(Value::Number(7)));
+test_error!(int_of_nan, "int(0.0 / 0.0)", "1:5: can't convert float NaN to number");
+test!(int_of_float_wider_than_32_bits, r#"int(1e10)"#, Ok(Value::Number(10000000000)));
+test!(int_of_huge_float, r#"int(1e20)"#, Ok(Value::BigInt("100000000000000000000".parse().unwrap())));
+test_error!(int_of_infinity, "int(1.0 / 0)", "1:5: can't convert float inf to number");
+test_error!(float_of_string, "float(\"a\")", "1:7: expected number, found string a");
+test_error!(builtin_arity, "float(1,2)", "1:1: function 'float' takes 1 argument but 2 were given");